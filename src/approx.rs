@@ -0,0 +1,358 @@
+//! Approximate triangle counting over edge streams, for graphs too large
+//! to hold as a `StaticGraph`.
+//!
+//! Every estimator consumes edges as `Result<(V, V), LoadError>` items, so
+//! an `EdgeListReader` can be passed straight in, and takes a seed so runs
+//! are reproducible. Self-loops are skipped. Unless noted otherwise, each
+//! undirected edge should appear once in the stream.
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::errors::LoadError;
+use crate::simplegraphs::SimpleVertex;
+use crate::truss::threaded_edge_support;
+use crate::{csr_from_edges, StaticGraph};
+
+/// z-score for a two-sided 95% normal confidence interval.
+const Z95: f64 = 1.959_963_984_540_054;
+
+/// An estimated triangle count with its standard error and a 95%
+/// confidence interval from the normal approximation. `lower` is clamped
+/// at zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub value: f64,
+    pub std_error: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Estimate {
+    fn normal(value: f64, std_error: f64) -> Self {
+        Estimate {
+            value,
+            std_error,
+            lower: (value - Z95 * std_error).max(0.0),
+            upper: value + Z95 * std_error,
+        }
+    }
+}
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A splitmix64 generator: plenty for sampling, and seedable without
+/// pulling in a dependency.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.0)
+    }
+
+    /// A uniform integer in `0..n`. `n` must be positive.
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+fn unit(x: u64) -> f64 {
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// DOULION (Tsourakakis et al., 2009): keeps each edge with probability
+/// `p`, counts the triangles of the sparsified graph exactly, and scales
+/// by `1 / p^3`. Memory is proportional to the `p * m` edges kept.
+///
+/// The keep/drop decision for `{u, v}` is a hash of the edge and `seed`,
+/// so duplicate edges and both directions of an edge are kept or dropped
+/// together. The variance accounts for pairs of triangles sharing an edge,
+/// estimated from the per-edge support of the sparsified graph.
+pub fn doulion<V, I>(edges: I, p: f64, seed: u64) -> Result<Estimate, LoadError>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>,
+    I: IntoIterator<Item = Result<(V, V), LoadError>>,
+{
+    assert!(p > 0.0 && p <= 1.0, "sampling probability must be in (0, 1]");
+    let key = mix(seed);
+    let mut kept: Vec<(V, V)> = vec![];
+    let mut n = 0usize;
+    for e in edges {
+        let (u, v) = e?;
+        let (a, b): (usize, usize) = (u.min(v).as_(), u.max(v).as_());
+        if a != b && unit(mix(mix(key ^ a as u64).wrapping_add(b as u64))) < p {
+            kept.push((u, v));
+            kept.push((v, u));
+            n = n.max(b + 1);
+        }
+    }
+    let (indptr, indices) = csr_from_edges(n, kept);
+    let g = StaticGraph::from_csr(indptr, indices).expect("invalid CSR");
+    let support = threaded_edge_support(&g);
+
+    let t = support.iter().sum::<usize>() as f64 / 3.0;
+    let pairs: f64 = support.iter().map(|s| (s * s.saturating_sub(1) / 2) as f64).sum();
+    let t_hat = t / p.powi(3);
+    let pairs_hat = pairs / p.powi(5);
+    let var = t_hat * (p.powi(-3) - 1.0) + 2.0 * pairs_hat * (1.0 / p - 1.0);
+    Ok(Estimate::normal(t_hat, var.max(0.0).sqrt()))
+}
+
+fn stream_changed() -> LoadError {
+    LoadError::Format { line: 0, text: "edge stream changed between passes".to_string() }
+}
+
+/// Wedge sampling (Seshadhri, Pinar & Kolda, 2013) in three passes over
+/// the stream, which `open` must be able to replay identically.
+///
+/// The first pass counts degrees. `samples` wedges (paths of length two)
+/// are then drawn uniformly, the second pass finds their edges, and the
+/// third checks which of them are closed by an edge. The closed fraction
+/// estimates the transitivity, and the triangle count is a third of that
+/// times the number of wedges. Memory is O(n + samples).
+pub fn wedge_sampling<V, F, I>(mut open: F, samples: usize, seed: u64) -> Result<Estimate, LoadError>
+where
+    V: SimpleVertex,
+    F: FnMut() -> Result<I, LoadError>,
+    I: IntoIterator<Item = Result<(V, V), LoadError>>,
+{
+    assert!(samples > 0, "wedge sampling needs at least one sample");
+    let mut deg: Vec<u64> = vec![];
+    for e in open()? {
+        let (u, v) = e?;
+        let (u, v): (usize, usize) = (u.as_(), v.as_());
+        if u == v {
+            continue;
+        }
+        if deg.len() <= u.max(v) {
+            deg.resize(u.max(v) + 1, 0);
+        }
+        deg[u] += 1;
+        deg[v] += 1;
+    }
+    let mut cum: Vec<u64> = Vec::with_capacity(deg.len());
+    let mut wedges = 0u64;
+    for d in deg.iter() {
+        wedges += d * d.saturating_sub(1) / 2;
+        cum.push(wedges);
+    }
+    if wedges == 0 {
+        return Ok(Estimate::normal(0.0, 0.0));
+    }
+
+    // A wedge is its center plus two distinct positions among the center's
+    // edges, counted in stream order.
+    let mut rng = SplitMix64(seed);
+    let mut wanted: HashMap<(usize, u64), Vec<(usize, usize)>> = HashMap::new();
+    for k in 0..samples {
+        let x = rng.below(wedges);
+        let c = match cum.binary_search_by(|s| if *s <= x { Ordering::Less } else { Ordering::Greater }) {
+            Ok(i) | Err(i) => i,
+        };
+        let i = rng.below(deg[c]);
+        let mut j = rng.below(deg[c] - 1);
+        if j >= i {
+            j += 1;
+        }
+        wanted.entry((c, i)).or_default().push((k, 0));
+        wanted.entry((c, j)).or_default().push((k, 1));
+    }
+
+    let mut ends = vec![[usize::MAX; 2]; samples];
+    let mut seen = vec![0u64; deg.len()];
+    for e in open()? {
+        let (u, v) = e?;
+        let (u, v): (usize, usize) = (u.as_(), v.as_());
+        if u == v {
+            continue;
+        }
+        if u.max(v) >= deg.len() {
+            return Err(stream_changed());
+        }
+        for (c, other) in [(u, v), (v, u)].iter() {
+            if let Some(reqs) = wanted.get(&(*c, seen[*c])) {
+                for (k, slot) in reqs.iter() {
+                    ends[*k][*slot] = *other;
+                }
+            }
+            seen[*c] += 1;
+        }
+    }
+
+    let mut closing: HashMap<(usize, usize), usize> = HashMap::new();
+    for [a, b] in ends.iter() {
+        if *a == usize::MAX || *b == usize::MAX {
+            return Err(stream_changed());
+        }
+        if a != b {
+            *closing.entry((*a.min(b), *a.max(b))).or_insert(0) += 1;
+        }
+    }
+    let mut closed = 0usize;
+    for e in open()? {
+        let (u, v) = e?;
+        let (u, v): (usize, usize) = (u.as_(), v.as_());
+        if let Some(c) = closing.remove(&(u.min(v), u.max(v))) {
+            closed += c;
+        }
+    }
+
+    let s = samples as f64;
+    let kappa = closed as f64 / s;
+    let scale = wedges as f64 / 3.0;
+    Ok(Estimate::normal(kappa * scale, scale * (kappa * (1.0 - kappa) / s).sqrt()))
+}
+
+/// TRIEST-IMPR (De Stefani et al., 2016): a single pass keeping a uniform
+/// reservoir of at most `budget` edges.
+///
+/// Before each edge is offered to the reservoir, the triangles it closes
+/// with sampled edges are counted, weighted by the inverse probability that
+/// both other edges are in the sample. The standard error treats the
+/// triangles as independent, so the interval is optimistic on graphs where
+/// many triangles share edges.
+pub fn triest<V, I>(edges: I, budget: usize, seed: u64) -> Result<Estimate, LoadError>
+where
+    V: SimpleVertex,
+    I: IntoIterator<Item = Result<(V, V), LoadError>>,
+{
+    assert!(budget >= 2, "the reservoir must hold at least two edges");
+    let mut rng = SplitMix64(seed);
+    let mut sample: Vec<(usize, usize)> = Vec::with_capacity(budget);
+    let mut adj: HashMap<usize, HashSet<usize>> = HashMap::new();
+    let m = budget as f64;
+    let mut t = 0u64;
+    let mut tau = 0f64;
+    let mut var = 0f64;
+
+    for e in edges {
+        let (u, v) = e?;
+        let (u, v): (usize, usize) = (u.as_(), v.as_());
+        if u == v {
+            continue;
+        }
+        t += 1;
+        let tf = t as f64;
+        let eta = ((tf - 1.0) * (tf - 2.0) / (m * (m - 1.0))).max(1.0);
+        let common = match (adj.get(&u), adj.get(&v)) {
+            (Some(a), Some(b)) => {
+                let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+                small.iter().filter(|w| large.contains(w)).count()
+            }
+            _ => 0,
+        };
+        tau += eta * common as f64;
+        var += eta * (eta - 1.0) * common as f64;
+
+        let slot = if sample.len() < budget {
+            sample.push((u, v));
+            sample.len() - 1
+        } else if rng.below(t) < budget as u64 {
+            let i = rng.below(budget as u64) as usize;
+            let (a, b) = sample[i];
+            for (x, y) in [(a, b), (b, a)].iter() {
+                if let Some(s) = adj.get_mut(x) {
+                    s.remove(y);
+                    if s.is_empty() {
+                        adj.remove(x);
+                    }
+                }
+            }
+            sample[i] = (u, v);
+            i
+        } else {
+            continue;
+        };
+        let (a, b) = sample[slot];
+        adj.entry(a).or_default().insert(b);
+        adj.entry(b).or_default().insert(a);
+    }
+    Ok(Estimate::normal(tau, var.sqrt()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edgelist::EdgeListReader;
+    use crate::test_util::{graph, random_edges};
+    use crate::traits::FromEdgeList;
+    use crate::triangles::threaded_triangles;
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::Path;
+
+    fn testgraph() -> EdgeListReader<BufReader<File>, u32> {
+        EdgeListReader::new(BufReader::new(File::open("testgraph.txt").unwrap()))
+    }
+
+    /// Every edge of `g` once, as a stream.
+    fn stream(g: &StaticGraph<u32>) -> Vec<Result<(u32, u32), LoadError>> {
+        g.edge_index().iter().map(Ok).collect()
+    }
+
+    fn complete(n: u32) -> Vec<(u32, u32)> {
+        (0..n).flat_map(|u| ((u + 1)..n).map(move |v| (u, v))).collect()
+    }
+
+    #[test]
+    fn doulion_keeping_everything_is_exact() {
+        let g = StaticGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        let est = doulion(testgraph(), 1.0, 3).unwrap();
+        assert_eq!(est.value, threaded_triangles(&g) as f64);
+        assert_eq!(est.std_error, 0.0);
+
+        let g = graph(60, random_edges(60, 400, 9));
+        let est = doulion(stream(&g), 1.0, 3).unwrap();
+        assert_eq!(est.value, threaded_triangles(&g) as f64);
+        assert_eq!((est.lower, est.upper), (est.value, est.value));
+    }
+
+    #[test]
+    fn triest_with_room_for_every_edge_is_exact() {
+        let g = graph(60, random_edges(60, 400, 11));
+        let m = g.edge_index().len();
+        for budget in [m, m + 1, 2 * m].iter() {
+            let est = triest(stream(&g), *budget, 5).unwrap();
+            assert_eq!(est.value, threaded_triangles(&g) as f64);
+            assert_eq!(est.std_error, 0.0);
+        }
+    }
+
+    #[test]
+    fn wedge_sampling_on_a_clique_is_exact() {
+        for n in [3u32, 4, 10, 25].iter() {
+            let edges = complete(*n);
+            let est = wedge_sampling(|| Ok(edges.iter().cloned().map(Ok)), 200, 7).unwrap();
+            let n = *n as f64;
+            assert_eq!(est.value, n * (n - 1.0) * (n - 2.0) / 6.0);
+            assert_eq!(est.std_error, 0.0);
+        }
+        let est = wedge_sampling(|| Ok(Vec::<Result<(u32, u32), LoadError>>::new()), 10, 7).unwrap();
+        assert_eq!(est.value, 0.0);
+    }
+
+    #[test]
+    fn wedge_sampling_detects_a_changed_stream() {
+        let check = |passes: Vec<Vec<(u32, u32)>>| {
+            let mut passes = passes.into_iter();
+            let open = || Ok(passes.next().unwrap().into_iter().map(Ok));
+            match wedge_sampling(open, 50, 1) {
+                Err(LoadError::Format { text, .. }) => assert_eq!(text, "edge stream changed between passes"),
+                Err(e) => panic!("unexpected error: {}", e),
+                Ok(_) => panic!("expected an error"),
+            }
+        };
+        let k5 = complete(5);
+        // A vertex that was not there when degrees were counted.
+        let mut grown = k5.clone();
+        grown.push((0, 9));
+        check(vec![k5.clone(), grown, k5.clone()]);
+        // Edges missing from the second pass leave sampled wedges unfilled.
+        check(vec![k5.clone(), k5[..2].to_vec(), k5]);
+    }
+}
@@ -0,0 +1,604 @@
+//! A compact, versioned binary format for static graphs.
+//!
+//! A file is a 32-byte header followed by one CSR block (`StaticGraph`) or
+//! two (`fadj` then `badj`, `StaticDiGraph`). All integers are little-endian.
+//!
+//! ```text
+//! header:  magic "RGGB" | version: u16 | kind: u8 | width: u8 | reserved: [u8; 8]
+//!          | nv: u64 | nnz: u64
+//! block:   indptr: [u64; nv + 1] | indices: [V; nnz] | zero padding to 8 bytes
+//! ```
+//!
+//! `kind` is 0 for undirected and 1 for directed graphs; `width` is
+//! `size_of::<V>()`. Because every array starts on an 8-byte boundary, the
+//! `Mapped*` types can serve neighbor slices straight out of the mapping.
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::path::Path;
+use std::slice;
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::errors::LoadError;
+use crate::simplegraphs::{SimpleEdge, SimpleVertex};
+use crate::traits::{self, DirectedGraph, UndirectedGraph};
+use crate::{StaticDiGraph, StaticGraph, VOOR};
+
+pub const MAGIC: &[u8; 4] = b"RGGB";
+pub const VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 32;
+const UNDIRECTED: u8 = 0;
+const DIRECTED: u8 = 1;
+
+fn format_error(text: &str) -> LoadError {
+    LoadError::Format { line: 0, text: text.to_string() }
+}
+
+fn io_error(e: io::Error) -> LoadError {
+    LoadError::Io { line: 0, source: e }
+}
+
+fn padding(len: usize) -> usize {
+    (8 - len % 8) % 8
+}
+
+/// The size in bytes of a CSR block, or `None` if it overflows `usize`.
+fn checked_block_len(nv: usize, nnz: usize, width: usize) -> Option<usize> {
+    let ilen = nnz.checked_mul(width)?;
+    nv.checked_add(1)?.checked_mul(8)?.checked_add(ilen)?.checked_add(padding(ilen))
+}
+
+fn block_len(nv: usize, nnz: usize, width: usize) -> usize {
+    checked_block_len(nv, nnz, width).expect("graph too large for the binary format")
+}
+
+struct Header {
+    kind: u8,
+    width: u8,
+    nv: u64,
+    nnz: u64,
+}
+
+impl Header {
+    fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&[self.kind, self.width])?;
+        w.write_all(&[0u8; 8])?;
+        w.write_all(&self.nv.to_le_bytes())?;
+        w.write_all(&self.nnz.to_le_bytes())
+    }
+
+    fn read(buf: &[u8]) -> Result<Self, LoadError> {
+        if buf.len() < HEADER_LEN || &buf[0..4] != MAGIC {
+            return Err(format_error("not a binary graph file"));
+        }
+        let version = u16::from_le_bytes([buf[4], buf[5]]);
+        if version != VERSION {
+            return Err(format_error(&format!("unsupported version {}", version)));
+        }
+        let mut nv = [0u8; 8];
+        let mut nnz = [0u8; 8];
+        nv.copy_from_slice(&buf[16..24]);
+        nnz.copy_from_slice(&buf[24..32]);
+        Ok(Header {
+            kind: buf[6],
+            width: buf[7],
+            nv: u64::from_le_bytes(nv),
+            nnz: u64::from_le_bytes(nnz),
+        })
+    }
+
+    /// Checks that this header describes a graph of the given kind with
+    /// vertex type `V`, and returns `(nv, nnz)`.
+    fn check<V: SimpleVertex>(&self, kind: u8) -> Result<(usize, usize), LoadError> {
+        if self.kind != kind {
+            return Err(format_error("graph is of the wrong kind (directed/undirected)"));
+        }
+        if self.width as usize != mem::size_of::<V>() {
+            return Err(format_error(&format!(
+                "file stores {}-byte vertices, expected {}",
+                self.width,
+                mem::size_of::<V>()
+            )));
+        }
+        if cfg!(target_endian = "big") {
+            return Err(format_error("memory-mapped loading requires a little-endian host"));
+        }
+        let (nv, nnz) = match (usize::try_from(self.nv), usize::try_from(self.nnz)) {
+            (Ok(nv), Ok(nnz)) => (nv, nnz),
+            _ => return Err(format_error("header sizes overflow")),
+        };
+        if V::from(nv).is_none() {
+            return Err(format_error("vertex count does not fit the vertex type"));
+        }
+        Ok((nv, nnz))
+    }
+}
+
+fn write_csr<V, W>(w: &mut W, m: &graph_matrix::GraphMatrix<V>) -> io::Result<()>
+where
+    V: SimpleVertex,
+    W: Write,
+{
+    let mut offset = 0u64;
+    w.write_all(&offset.to_le_bytes())?;
+    for u in 0..m.dim() {
+        offset += m.row(V::from(u).expect(VOOR)).len() as u64;
+        w.write_all(&offset.to_le_bytes())?;
+    }
+    let width = mem::size_of::<V>();
+    for u in 0..m.dim() {
+        for v in m.row(V::from(u).expect(VOOR)) {
+            let x: usize = v.as_();
+            w.write_all(&(x as u64).to_le_bytes()[..width])?;
+        }
+    }
+    w.write_all(&[0u8; 8][..padding(m.n() * width)])
+}
+
+impl<V> StaticGraph<V>
+where
+    V: SimpleVertex,
+{
+    /// The size in bytes of this graph in the binary format.
+    pub fn binary_len(&self) -> usize {
+        HEADER_LEN + block_len(self.adj.dim(), self.adj.n(), mem::size_of::<V>())
+    }
+
+    /// Writes the graph in the binary format described in the module docs.
+    pub fn write_binary<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let header = Header {
+            kind: UNDIRECTED,
+            width: mem::size_of::<V>() as u8,
+            nv: self.adj.dim() as u64,
+            nnz: self.adj.n() as u64,
+        };
+        header.write(&mut w)?;
+        write_csr(&mut w, &self.adj)?;
+        w.flush()
+    }
+}
+
+impl<V> StaticDiGraph<V>
+where
+    V: SimpleVertex,
+{
+    /// The size in bytes of this graph in the binary format.
+    pub fn binary_len(&self) -> usize {
+        HEADER_LEN + 2 * block_len(self.fadj.dim(), self.fadj.n(), mem::size_of::<V>())
+    }
+
+    /// Writes the graph in the binary format described in the module docs.
+    pub fn write_binary<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let header = Header {
+            kind: DIRECTED,
+            width: mem::size_of::<V>() as u8,
+            nv: self.fadj.dim() as u64,
+            nnz: self.fadj.n() as u64,
+        };
+        header.write(&mut w)?;
+        write_csr(&mut w, &self.fadj)?;
+        write_csr(&mut w, &self.badj)?;
+        w.flush()
+    }
+}
+
+/// Byte offsets of one CSR block inside a mapping.
+#[derive(Clone, Copy)]
+struct CsrBlock {
+    nv: usize,
+    nnz: usize,
+    indptr: usize,
+    indices: usize,
+}
+
+impl CsrBlock {
+    /// Lays out a block starting at `offset` in a mapping of `limit` bytes,
+    /// returning it and the offset just past its end. Fails if the sizes
+    /// taken from the header overflow or the block runs past `limit`; this
+    /// is the only check standing between a corrupt header and the unsafe
+    /// slices below.
+    fn at<V>(offset: usize, nv: usize, nnz: usize, limit: usize) -> Result<(Self, usize), LoadError> {
+        let too_large = || format_error("header sizes overflow");
+        let indices = nv.checked_add(1).and_then(|x| x.checked_mul(8)).and_then(|x| x.checked_add(offset));
+        let end = checked_block_len(nv, nnz, mem::size_of::<V>()).and_then(|x| x.checked_add(offset));
+        let (indices, end) = match (indices, end) {
+            (Some(i), Some(e)) => (i, e),
+            _ => return Err(too_large()),
+        };
+        if end > limit {
+            return Err(format_error("file is truncated"));
+        }
+        Ok((CsrBlock { nv, nnz, indptr: offset, indices }, end))
+    }
+
+    fn indptr<'a>(&self, buf: &'a [u8]) -> &'a [u64] {
+        // Safe: `indptr` is 8-byte aligned within a page-aligned mapping, and
+        // `at` checked, without overflow, that the whole block lies inside it.
+        unsafe { slice::from_raw_parts(buf.as_ptr().add(self.indptr) as *const u64, self.nv + 1) }
+    }
+
+    fn indices<'a, V>(&self, buf: &'a [u8]) -> &'a [V] {
+        // Safe for the same reasons as `indptr`; `SimpleVertex` is only
+        // implemented for unsigned integers, for which any bit pattern is valid.
+        unsafe { slice::from_raw_parts(buf.as_ptr().add(self.indices) as *const V, self.nnz) }
+    }
+
+    fn row<'a, V>(&self, buf: &'a [u8], v: usize) -> &'a [V] {
+        let indptr = self.indptr(buf);
+        &self.indices(buf)[indptr[v] as usize..indptr[v + 1] as usize]
+    }
+
+    fn validate<V: SimpleVertex>(&self, buf: &[u8]) -> Result<(), LoadError> {
+        let indptr = self.indptr(buf);
+        if indptr[0] != 0 || indptr[self.nv] as usize != self.nnz {
+            return Err(format_error("indptr does not span indices"));
+        }
+        if indptr.windows(2).any(|w| w[0] > w[1]) {
+            return Err(format_error("indptr is not monotonic"));
+        }
+        for v in 0..self.nv {
+            let row: &[V] = self.row(buf, v);
+            if row.iter().any(|x| x.as_() >= self.nv) {
+                return Err(format_error(&format!("row {} has an out-of-range vertex", v)));
+            }
+            if row.windows(2).any(|w| w[0] > w[1]) {
+                return Err(format_error(&format!("row {} is not sorted", v)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn map_file(fname: &Path) -> Result<Mmap, LoadError> {
+    let f = File::open(fname).map_err(io_error)?;
+    unsafe { Mmap::map(&f) }.map_err(io_error)
+}
+
+/// Copies `len` bytes produced by `write` into an anonymous read-only mapping.
+fn map_anon<F>(len: usize, write: F) -> Result<Mmap, LoadError>
+where
+    F: FnOnce(&mut [u8]) -> io::Result<()>,
+{
+    let mut mm = MmapMut::map_anon(len).map_err(io_error)?;
+    write(&mut mm[..]).map_err(io_error)?;
+    mm.make_read_only().map_err(io_error)
+}
+
+/// An undirected graph backed by a memory-mapped binary file. Neighbor
+/// slices point directly into the mapping; nothing is copied on load.
+pub struct MappedGraph<V> {
+    mmap: Mmap,
+    adj: CsrBlock,
+    ne: usize,
+    _v: PhantomData<V>,
+}
+
+impl<V> MappedGraph<V>
+where
+    V: SimpleVertex,
+{
+    /// Maps and validates a file written by `StaticGraph::write_binary`.
+    pub fn open(fname: &Path) -> Result<Self, LoadError> {
+        Self::from_mmap(map_file(fname)?)
+    }
+
+    pub fn from_mmap(mmap: Mmap) -> Result<Self, LoadError> {
+        let (nv, nnz) = Header::read(&mmap)?.check::<V>(UNDIRECTED)?;
+        let (adj, _) = CsrBlock::at::<V>(HEADER_LEN, nv, nnz, mmap.len())?;
+        adj.validate::<V>(&mmap)?;
+        // Self-loops are stored once, every other edge twice.
+        let loops = (0..nv)
+            .filter(|v| adj.row::<V>(&mmap, *v).binary_search(&V::from(*v).expect(VOOR)).is_ok())
+            .count();
+        let ne = (nnz + loops) / 2;
+        Ok(MappedGraph { mmap, adj, ne, _v: PhantomData })
+    }
+
+    /// Copies an in-memory graph into an anonymous mapping.
+    pub fn from_static(g: &StaticGraph<V>) -> Result<Self, LoadError> {
+        Self::from_mmap(map_anon(g.binary_len(), |buf| g.write_binary(buf))?)
+    }
+}
+
+impl<V> traits::Graph<V, SimpleEdge<V>> for MappedGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    type VIT = std::ops::Range<V>;
+    fn nv(&self) -> V::T {
+        V::T::from(self.adj.nv).unwrap()
+    }
+
+    fn ne(&self) -> usize {
+        self.ne
+    }
+
+    fn vertices(&self) -> std::ops::Range<V> {
+        std::ops::Range {
+            start: V::zero(),
+            end: V::from(self.adj.nv).expect(VOOR),
+        }
+    }
+
+    fn out_degree(&self, v: V) -> V {
+        V::from(self.out_neighbors(v).len()).expect(VOOR)
+    }
+
+    fn in_degree(&self, v: V) -> V { self.out_degree(v) }
+
+    fn has_edge(&self, e: SimpleEdge<V>) -> bool {
+        let (s, d) = if self.out_degree(e.src) < self.out_degree(e.dst) {
+            (e.src, e.dst)
+        } else {
+            (e.dst, e.src)
+        };
+        self.out_neighbors(s).binary_search(&d).is_ok()
+    }
+
+    fn out_neighbors(&self, v: V) -> &[V] {
+        self.adj.row(&self.mmap, v.as_())
+    }
+
+    fn in_neighbors(&self, v: V) -> &[V] {
+        self.out_neighbors(v)
+    }
+}
+
+impl<V> traits::FromEdgeList for MappedGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    fn try_from_edge_reader<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        let g = StaticGraph::try_from_edge_reader(reader)?;
+        Self::from_static(&g)
+    }
+}
+
+impl<V> UndirectedGraph<V, SimpleEdge<V>> for MappedGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+}
+
+/// A directed graph backed by a memory-mapped binary file. Neighbor
+/// slices point directly into the mapping; nothing is copied on load.
+pub struct MappedDiGraph<V> {
+    mmap: Mmap,
+    fadj: CsrBlock,
+    badj: CsrBlock,
+    _v: PhantomData<V>,
+}
+
+impl<V> MappedDiGraph<V>
+where
+    V: SimpleVertex,
+{
+    /// Maps and validates a file written by `StaticDiGraph::write_binary`.
+    pub fn open(fname: &Path) -> Result<Self, LoadError> {
+        Self::from_mmap(map_file(fname)?)
+    }
+
+    pub fn from_mmap(mmap: Mmap) -> Result<Self, LoadError> {
+        let (nv, nnz) = Header::read(&mmap)?.check::<V>(DIRECTED)?;
+        let (fadj, mid) = CsrBlock::at::<V>(HEADER_LEN, nv, nnz, mmap.len())?;
+        let (badj, _) = CsrBlock::at::<V>(mid, nv, nnz, mmap.len())?;
+        fadj.validate::<V>(&mmap)?;
+        badj.validate::<V>(&mmap)?;
+        Ok(MappedDiGraph { mmap, fadj, badj, _v: PhantomData })
+    }
+
+    /// Copies an in-memory graph into an anonymous mapping.
+    pub fn from_static(g: &StaticDiGraph<V>) -> Result<Self, LoadError> {
+        Self::from_mmap(map_anon(g.binary_len(), |buf| g.write_binary(buf))?)
+    }
+}
+
+impl<V> traits::Graph<V, SimpleEdge<V>> for MappedDiGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    type VIT = std::ops::Range<V>;
+    fn nv(&self) -> V::T {
+        V::T::from(self.fadj.nv).unwrap()
+    }
+
+    fn ne(&self) -> usize {
+        self.fadj.nnz
+    }
+
+    fn vertices(&self) -> std::ops::Range<V> {
+        std::ops::Range {
+            start: V::zero(),
+            end: V::from(self.fadj.nv).expect(VOOR),
+        }
+    }
+
+    fn out_degree(&self, v: V) -> V {
+        V::from(self.out_neighbors(v).len()).expect(VOOR)
+    }
+
+    fn in_degree(&self, v: V) -> V {
+        V::from(self.in_neighbors(v).len()).expect(VOOR)
+    }
+
+    fn has_edge(&self, e: SimpleEdge<V>) -> bool {
+        let s = e.src;
+        let d = e.dst;
+        if self.out_degree(s) < self.in_degree(d) {
+            self.out_neighbors(s).binary_search(&d).is_ok()
+        } else {
+            self.in_neighbors(d).binary_search(&s).is_ok()
+        }
+    }
+
+    fn in_neighbors(&self, v: V) -> &[V] {
+        self.badj.row(&self.mmap, v.as_())
+    }
+
+    fn out_neighbors(&self, v: V) -> &[V] {
+        self.fadj.row(&self.mmap, v.as_())
+    }
+}
+
+impl<V> traits::FromEdgeList for MappedDiGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    fn try_from_edge_reader<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        let g = StaticDiGraph::try_from_edge_reader(reader)?;
+        Self::from_static(&g)
+    }
+}
+
+impl<V> DirectedGraph<V, SimpleEdge<V>> for MappedDiGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{digraph, graph, random_edges};
+    use crate::traits::{FromEdgeList, Graph};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// A path in the temp directory that is removed again on drop.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            TempFile(std::env::temp_dir().join(format!("rustgraphs-{}-{}", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn bytes_of<V: SimpleVertex>(g: &StaticGraph<V>) -> Vec<u8> {
+        let mut buf = vec![];
+        g.write_binary(&mut buf).unwrap();
+        assert_eq!(buf.len(), g.binary_len());
+        buf
+    }
+
+    fn map_bytes(bytes: &[u8]) -> Mmap {
+        map_anon(bytes.len(), |buf| {
+            buf.copy_from_slice(bytes);
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    fn format_error_text<T>(r: Result<T, LoadError>) -> String {
+        match r {
+            Err(LoadError::Format { line: 0, text }) => text,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    fn set_u64(bytes: &mut [u8], at: usize, x: u64) {
+        bytes[at..at + 8].copy_from_slice(&x.to_le_bytes());
+    }
+
+    #[test]
+    fn round_trip_through_file() {
+        let mut edges = random_edges(40, 120, 3);
+        edges.push((7, 7));
+        let g = graph(45, edges.clone());
+        let f = TempFile::new("undirected.bin");
+        g.write_binary(fs::File::create(&f.0).unwrap()).unwrap();
+        let m = MappedGraph::<u32>::open(&f.0).unwrap();
+        assert_eq!((m.nv(), m.ne()), (g.nv(), g.ne()));
+        for v in g.vertices() {
+            assert_eq!(m.out_neighbors(v), g.out_neighbors(v));
+        }
+
+        let d = digraph(45, edges);
+        let f = TempFile::new("directed.bin");
+        d.write_binary(fs::File::create(&f.0).unwrap()).unwrap();
+        let m = MappedDiGraph::<u32>::open(&f.0).unwrap();
+        assert_eq!((m.nv(), m.ne()), (d.nv(), d.ne()));
+        for v in d.vertices() {
+            assert_eq!(m.out_neighbors(v), d.out_neighbors(v));
+            assert_eq!(m.in_neighbors(v), d.in_neighbors(v));
+        }
+    }
+
+    #[test]
+    fn round_trip_through_memory() {
+        let g = StaticGraph::<u8>::from_edge_file(Path::new("testgraph.txt"));
+        let m = MappedGraph::from_static(&g).unwrap();
+        assert_eq!((m.nv(), m.ne()), (g.nv(), g.ne()));
+        for v in g.vertices() {
+            assert_eq!(m.out_neighbors(v), g.out_neighbors(v));
+        }
+        let m = MappedGraph::from_static(&graph(0, vec![])).unwrap();
+        assert_eq!((m.nv(), m.ne()), (0, 0));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = bytes_of(&graph(10, random_edges(10, 20, 1)));
+        for len in [0, 4, HEADER_LEN - 1, HEADER_LEN, HEADER_LEN + 8, bytes.len() - 1].iter() {
+            assert!(MappedGraph::<u32>::from_mmap(map_bytes(&bytes[..*len])).is_err(), "length {}", len);
+        }
+        assert_eq!(format_error_text(MappedGraph::<u32>::from_mmap(map_bytes(&bytes[..bytes.len() - 1]))), "file is truncated");
+    }
+
+    #[test]
+    fn rejects_wrong_kind_or_width() {
+        let bytes = bytes_of(&graph(10, random_edges(10, 20, 1)));
+        let text = format_error_text(MappedDiGraph::<u32>::from_mmap(map_bytes(&bytes)));
+        assert!(text.contains("wrong kind"), "{}", text);
+        let text = format_error_text(MappedGraph::<u64>::from_mmap(map_bytes(&bytes)));
+        assert_eq!(text, "file stores 4-byte vertices, expected 8");
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(format_error_text(MappedGraph::<u32>::from_mmap(map_bytes(&bad))), "not a binary graph file");
+    }
+
+    #[test]
+    fn rejects_sizes_that_overflow() {
+        let (indptr, indices) = crate::csr_from_edges(3, vec![(0u64, 1u64), (1, 0)]);
+        let wide = bytes_of(&StaticGraph::from_csr(indptr, indices).unwrap());
+        let narrow = bytes_of(&graph(10, random_edges(10, 20, 1)));
+
+        // `nv + 1` overflows.
+        let mut bad = wide.clone();
+        set_u64(&mut bad, 16, u64::MAX);
+        assert_eq!(format_error_text(MappedGraph::<u64>::from_mmap(map_bytes(&bad))), "header sizes overflow");
+        // `(nv + 1) * 8` overflows.
+        set_u64(&mut bad, 16, u64::MAX / 4);
+        assert_eq!(format_error_text(MappedGraph::<u64>::from_mmap(map_bytes(&bad))), "header sizes overflow");
+        // `nnz * width` overflows.
+        let mut bad = wide;
+        set_u64(&mut bad, 24, u64::MAX / 2);
+        assert_eq!(format_error_text(MappedGraph::<u64>::from_mmap(map_bytes(&bad))), "header sizes overflow");
+        // `nv` does not fit the vertex type.
+        let mut bad = narrow.clone();
+        set_u64(&mut bad, 16, 1 << 32);
+        assert_eq!(format_error_text(MappedGraph::<u32>::from_mmap(map_bytes(&bad))), "vertex count does not fit the vertex type");
+        // Sizes that add up without overflowing but point far past the end.
+        let mut bad = narrow;
+        set_u64(&mut bad, 24, 1 << 40);
+        assert_eq!(format_error_text(MappedGraph::<u32>::from_mmap(map_bytes(&bad))), "file is truncated");
+    }
+}
@@ -0,0 +1,501 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::simplegraphs::SimpleVertex;
+use crate::traits::Graph;
+use crate::{csr_from_edges, StaticDiGraph, StaticGraph, VOOR};
+
+/// A partition of the vertices into components: `labels[v]` is the
+/// component of `v`, numbered from 0, and `sizes[c]` is the number of
+/// vertices in component `c`.
+pub struct Components<V> {
+    pub labels: Vec<V>,
+    pub sizes: Vec<usize>,
+}
+
+impl<V> Components<V>
+where
+    V: SimpleVertex,
+{
+    fn from_labels(labels: Vec<usize>, n_components: usize) -> Self {
+        let mut sizes = vec![0usize; n_components];
+        for l in labels.iter() {
+            sizes[*l] += 1;
+        }
+        let labels = labels.into_iter().map(|l| V::from(l).expect(VOOR)).collect();
+        Components { labels, sizes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    /// The condensation of `g` with respect to these (strongly connected)
+    /// components: one vertex per component, and an edge between two
+    /// components whenever `g` has an edge between their members. The
+    /// result is a DAG.
+    pub fn condensation(&self, g: &StaticDiGraph<V>) -> StaticDiGraph<V>
+    where
+        std::ops::Range<V>: Iterator<Item=V>
+    {
+        let mut edges: Vec<(V, V)> = vec![];
+        for u in g.vertices() {
+            let lu = self.labels[u.as_()];
+            for v in g.out_neighbors(u) {
+                let lv = self.labels[v.as_()];
+                if lu != lv {
+                    edges.push((lu, lv));
+                }
+            }
+        }
+        let (indptr, indices) = csr_from_edges(self.len(), edges);
+        StaticDiGraph::from_csr(indptr, indices).expect("invalid CSR")
+    }
+}
+
+/// Strongly connected components by an iterative version of Tarjan's
+/// algorithm. Components are numbered in reverse topological order of the
+/// condensation: a component only has edges to lower-numbered components.
+pub fn strongly_connected_components<V>(g: &StaticDiGraph<V>) -> Components<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let n: usize = g.nv().as_();
+    let mut index = vec![usize::MAX; n];
+    let mut low = vec![0usize; n];
+    let mut on_stack = vec![false; n];
+    let mut labels = vec![0usize; n];
+    let mut scc_stack: Vec<V> = vec![];
+    let mut call_stack: Vec<(V, usize)> = vec![];
+    let mut counter = 0usize;
+    let mut n_components = 0usize;
+
+    for root in g.vertices() {
+        if index[root.as_()] != usize::MAX {
+            continue;
+        }
+        index[root.as_()] = counter;
+        low[root.as_()] = counter;
+        counter += 1;
+        scc_stack.push(root);
+        on_stack[root.as_()] = true;
+        call_stack.push((root, 0));
+
+        while let Some(top) = call_stack.last_mut() {
+            let u = top.0;
+            let uu = u.as_();
+            let nbrs = g.out_neighbors(u);
+            if top.1 < nbrs.len() {
+                let w = nbrs[top.1];
+                top.1 += 1;
+                let wu = w.as_();
+                if index[wu] == usize::MAX {
+                    index[wu] = counter;
+                    low[wu] = counter;
+                    counter += 1;
+                    scc_stack.push(w);
+                    on_stack[wu] = true;
+                    call_stack.push((w, 0));
+                } else if on_stack[wu] {
+                    low[uu] = low[uu].min(index[wu]);
+                }
+                continue;
+            }
+            call_stack.pop();
+            if let Some(&(p, _)) = call_stack.last() {
+                low[p.as_()] = low[p.as_()].min(low[uu]);
+            }
+            if low[uu] == index[uu] {
+                loop {
+                    let w = scc_stack.pop().unwrap();
+                    on_stack[w.as_()] = false;
+                    labels[w.as_()] = n_components;
+                    if w == u {
+                        break;
+                    }
+                }
+                n_components += 1;
+            }
+        }
+    }
+    Components::from_labels(labels, n_components)
+}
+
+const DONE: usize = usize::MAX;
+
+struct FbState {
+    color: Vec<AtomicUsize>,
+    labels: Vec<AtomicUsize>,
+    /// In- and out-degrees within the vertex's current subproblem, kept
+    /// up to date while trimming it.
+    indeg: Vec<AtomicUsize>,
+    outdeg: Vec<AtomicUsize>,
+    next_color: AtomicUsize,
+    next_label: AtomicUsize,
+}
+
+/// Parallel strongly connected components by forward-backward decomposition
+/// (Fleischer, Hendrickson & Pınar, 2000), using `badj` for the backward
+/// searches.
+///
+/// Each subproblem is a set of vertices sharing a color. Before a pivot is
+/// picked, the subproblem is trimmed: vertices with no in- or out-edges
+/// inside it are peeled off as singleton components, repeatedly, so long
+/// chains cost linear rather than quadratic time (FW-BW-Trim). The forward
+/// and backward reachable sets of the pivot then intersect in its
+/// component, and the three leftover sets become independent subproblems
+/// that run as separate rayon tasks. Component numbering is arbitrary.
+pub fn par_strongly_connected_components<V>(g: &StaticDiGraph<V>) -> Components<V>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let n: usize = g.nv().as_();
+    let st = FbState {
+        color: (0..n).map(|_| AtomicUsize::new(0)).collect(),
+        labels: (0..n).map(|_| AtomicUsize::new(0)).collect(),
+        indeg: (0..n).map(|_| AtomicUsize::new(0)).collect(),
+        outdeg: (0..n).map(|_| AtomicUsize::new(0)).collect(),
+        next_color: AtomicUsize::new(1),
+        next_label: AtomicUsize::new(0),
+    };
+
+    rayon::scope(|s| fb_spawn(s, g, &st, (0..n).collect(), 0));
+
+    let n_components = st.next_label.into_inner();
+    let labels = st.labels.into_iter().map(|l| l.into_inner()).collect();
+    Components::from_labels(labels, n_components)
+}
+
+fn fb_spawn<'s, V>(s: &rayon::Scope<'s>, g: &'s StaticDiGraph<V>, st: &'s FbState, verts: Vec<usize>, c: usize)
+where
+    V: SimpleVertex + Send + Sync,
+{
+    if !verts.is_empty() {
+        s.spawn(move |s| fb_step(s, g, st, verts, c));
+    }
+}
+
+/// Repeatedly removes the vertices of the subproblem `verts` (all of color
+/// `c`) that have no in- or out-neighbors of color `c`, making each its own
+/// component. Returns the vertices that are left. Takes time linear in the
+/// edges of the subproblem, however long the chains being peeled.
+fn fb_trim<V>(g: &StaticDiGraph<V>, st: &FbState, verts: Vec<usize>, c: usize) -> Vec<usize>
+where
+    V: SimpleVertex,
+{
+    let live = |w: usize, v: usize| w != v && st.color[w].load(Ordering::Relaxed) == c;
+    let mut queue: Vec<usize> = vec![];
+    for v in verts.iter().cloned() {
+        let vv = V::from(v).expect(VOOR);
+        let i = g.badj.row(vv).iter().filter(|w| live(w.as_(), v)).count();
+        let o = g.fadj.row(vv).iter().filter(|w| live(w.as_(), v)).count();
+        st.indeg[v].store(i, Ordering::Relaxed);
+        st.outdeg[v].store(o, Ordering::Relaxed);
+        if i == 0 || o == 0 {
+            queue.push(v);
+        }
+    }
+
+    // A vertex is queued when the first of its two degrees reaches zero,
+    // so it is queued at most once.
+    while let Some(v) = queue.pop() {
+        st.color[v].store(DONE, Ordering::Relaxed);
+        st.labels[v].store(st.next_label.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        let vv = V::from(v).expect(VOOR);
+        for w in g.fadj.row(vv) {
+            let wu = w.as_();
+            if live(wu, v)
+                && st.indeg[wu].fetch_sub(1, Ordering::Relaxed) == 1
+                && st.outdeg[wu].load(Ordering::Relaxed) > 0
+            {
+                queue.push(wu);
+            }
+        }
+        for w in g.badj.row(vv) {
+            let wu = w.as_();
+            if live(wu, v)
+                && st.outdeg[wu].fetch_sub(1, Ordering::Relaxed) == 1
+                && st.indeg[wu].load(Ordering::Relaxed) > 0
+            {
+                queue.push(wu);
+            }
+        }
+    }
+    verts.into_iter().filter(|v| st.color[*v].load(Ordering::Relaxed) == c).collect()
+}
+
+/// Trims the subproblem `verts` (all of color `c`), peels the component of
+/// a pivot off what is left, and spawns the three remaining subproblems.
+fn fb_step<'s, V>(s: &rayon::Scope<'s>, g: &'s StaticDiGraph<V>, st: &'s FbState, verts: Vec<usize>, c: usize)
+where
+    V: SimpleVertex + Send + Sync,
+{
+    let verts = fb_trim(g, st, verts, c);
+    if verts.is_empty() {
+        return;
+    }
+    let fc = st.next_color.fetch_add(1, Ordering::Relaxed);
+    let bc = st.next_color.fetch_add(1, Ordering::Relaxed);
+    let label = st.next_label.fetch_add(1, Ordering::Relaxed);
+    let color = |v: usize| st.color[v].load(Ordering::Relaxed);
+    let paint = |v: usize, to: usize| st.color[v].store(to, Ordering::Relaxed);
+
+    let pivot = verts[0];
+    let mut fwd: Vec<usize> = vec![pivot];
+    paint(pivot, fc);
+    let mut i = 0;
+    while i < fwd.len() {
+        let u = V::from(fwd[i]).expect(VOOR);
+        for w in g.fadj.row(u) {
+            let wu = w.as_();
+            if color(wu) == c {
+                paint(wu, fc);
+                fwd.push(wu);
+            }
+        }
+        i += 1;
+    }
+
+    // Anything reached backwards that was also reached forwards is in the
+    // pivot's component.
+    let mut bwd: Vec<usize> = vec![];
+    let mut frontier: Vec<usize> = vec![pivot];
+    paint(pivot, DONE);
+    st.labels[pivot].store(label, Ordering::Relaxed);
+    while let Some(u) = frontier.pop() {
+        for w in g.badj.row(V::from(u).expect(VOOR)) {
+            let wu = w.as_();
+            let cw = color(wu);
+            if cw == fc {
+                paint(wu, DONE);
+                st.labels[wu].store(label, Ordering::Relaxed);
+                frontier.push(wu);
+            } else if cw == c {
+                paint(wu, bc);
+                bwd.push(wu);
+                frontier.push(wu);
+            }
+        }
+    }
+
+    let fwd: Vec<usize> = fwd.into_iter().filter(|v| color(*v) == fc).collect();
+    let bwd: Vec<usize> = bwd.into_iter().filter(|v| color(*v) == bc).collect();
+    let rest: Vec<usize> = verts.into_iter().filter(|v| color(*v) == c).collect();
+    fb_spawn(s, g, st, fwd, fc);
+    fb_spawn(s, g, st, bwd, bc);
+    fb_spawn(s, g, st, rest, c);
+}
+
+fn find(parent: &mut [usize], mut v: usize) -> usize {
+    while parent[v] != v {
+        parent[v] = parent[parent[v]];
+        v = parent[v];
+    }
+    v
+}
+
+/// Connected components by sequential union-find. `labels[v]` is the
+/// smallest vertex in the component of `v`, matching
+/// `connected_components`.
+pub fn connected_components_seq<V>(g: &StaticGraph<V>) -> Vec<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let n: usize = g.nv().as_();
+    let mut parent: Vec<usize> = (0..n).collect();
+    for u in g.vertices() {
+        for v in g.out_neighbors(u) {
+            let ru = find(&mut parent, u.as_());
+            let rv = find(&mut parent, v.as_());
+            if ru < rv {
+                parent[rv] = ru;
+            } else if rv < ru {
+                parent[ru] = rv;
+            }
+        }
+    }
+    (0..n).map(|v| V::from(find(&mut parent, v)).expect(VOOR)).collect()
+}
+
+/// Hooks the trees containing `u` and `v` together, always pointing the
+/// larger root at the smaller one.
+fn link(u: usize, v: usize, comp: &[AtomicUsize]) {
+    let mut p1 = comp[u].load(Ordering::Relaxed);
+    let mut p2 = comp[v].load(Ordering::Relaxed);
+    while p1 != p2 {
+        let high = p1.max(p2);
+        let low = p1.min(p2);
+        let p_high = comp[high].load(Ordering::Relaxed);
+        if p_high == low {
+            break;
+        }
+        if p_high == high
+            && comp[high].compare_exchange(high, low, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+        {
+            break;
+        }
+        p1 = comp[comp[high].load(Ordering::Relaxed)].load(Ordering::Relaxed);
+        p2 = comp[low].load(Ordering::Relaxed);
+    }
+}
+
+fn compress(comp: &[AtomicUsize]) {
+    (0..comp.len()).into_par_iter().for_each(|v| loop {
+        let p = comp[v].load(Ordering::Relaxed);
+        let pp = comp[p].load(Ordering::Relaxed);
+        if p == pp {
+            break;
+        }
+        comp[v].store(pp, Ordering::Relaxed);
+    });
+}
+
+/// Connected components by parallel union-find with Afforest-style
+/// neighbor sampling (Sutton, Ben-Nun & Barak, 2018).
+///
+/// The first few neighbors of every vertex are linked and compressed to
+/// find the (probably) largest component early; the remaining edges are
+/// then only linked for vertices outside it. `labels[v]` is the smallest
+/// vertex in the component of `v`.
+pub fn connected_components<V>(g: &StaticGraph<V>) -> Vec<V>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    const NEIGHBOR_ROUNDS: usize = 2;
+    const NUM_SAMPLES: usize = 1024;
+
+    let n: usize = g.nv().as_();
+    let comp: Vec<AtomicUsize> = (0..n).map(AtomicUsize::new).collect();
+    let row = |u: usize| g.out_neighbors(V::from(u).expect(VOOR));
+
+    for r in 0..NEIGHBOR_ROUNDS {
+        (0..n).into_par_iter().for_each(|u| {
+            if let Some(v) = row(u).get(r) {
+                link(u, v.as_(), &comp);
+            }
+        });
+        compress(&comp);
+    }
+
+    // Sample evenly spaced vertices for the most common component so far.
+    let mut counts = std::collections::HashMap::new();
+    for v in (0..n).step_by((n / NUM_SAMPLES).max(1)) {
+        *counts.entry(comp[v].load(Ordering::Relaxed)).or_insert(0usize) += 1;
+    }
+    let frequent = counts.into_iter().max_by_key(|(_, c)| *c).map(|(l, _)| l);
+
+    // Each edge appears in both rows, so an edge with one end in the
+    // frequent component is still linked from the other end.
+    (0..n).into_par_iter().for_each(|u| {
+        if Some(comp[u].load(Ordering::Relaxed)) == frequent {
+            return;
+        }
+        for v in row(u).iter().skip(NEIGHBOR_ROUNDS) {
+            link(u, v.as_(), &comp);
+        }
+    });
+    compress(&comp);
+
+    comp.into_iter().map(|c| V::from(c.into_inner()).expect(VOOR)).collect()
+}
+
+/// The largest connected component as an induced subgraph, along with the
+/// original id of each of its vertices.
+pub fn largest_component<V>(g: &StaticGraph<V>) -> (StaticGraph<V>, Vec<V>)
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let labels = connected_components(g);
+    let mut sizes = vec![0usize; labels.len()];
+    for l in labels.iter() {
+        sizes[l.as_()] += 1;
+    }
+    let best = (0..sizes.len()).max_by_key(|l| sizes[*l]).unwrap_or(0);
+    let keep: Vec<bool> = labels.iter().map(|l| l.as_() == best).collect();
+    g.induced_subgraph(&keep)
+}
+
+/// Whether every vertex is reachable from every other. The empty graph is
+/// considered connected.
+pub fn is_connected<V>(g: &StaticGraph<V>) -> bool
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    connected_components(g).iter().all(|l| *l == V::zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dag::is_acyclic;
+    use crate::test_util::{digraph, graph, random_edges};
+    use crate::traits::FromEdgeList;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    /// Whether two labelings split the vertices into the same classes.
+    fn same_partition<A: Copy + Eq + std::hash::Hash, B: Copy + Eq + std::hash::Hash>(a: &[A], b: &[B]) -> bool {
+        let mut ab: HashMap<A, B> = HashMap::new();
+        let mut ba: HashMap<B, A> = HashMap::new();
+        a.len() == b.len()
+            && a.iter().zip(b.iter()).all(|(x, y)| *ab.entry(*x).or_insert(*y) == *y && *ba.entry(*y).or_insert(*x) == *x)
+    }
+
+    fn check_cc(g: &StaticGraph<u32>) {
+        let seq = connected_components_seq(g);
+        let par = connected_components(g);
+        // Both label each vertex with the smallest vertex of its component.
+        assert_eq!(seq, par);
+    }
+
+    fn check_scc(g: &StaticDiGraph<u32>) {
+        let seq = strongly_connected_components(g);
+        let par = par_strongly_connected_components(g);
+        assert_eq!(seq.len(), par.len());
+        assert!(same_partition(&seq.labels, &par.labels));
+        assert!(is_acyclic(&seq.condensation(g)));
+        assert!(is_acyclic(&par.condensation(g)));
+    }
+
+    #[test]
+    fn par_scc_matches_tarjan() {
+        check_scc(&StaticDiGraph::from_edge_file(Path::new("testgraph.txt")));
+        // A long path, which only trimming handles in linear time.
+        check_scc(&digraph(2000, (0..1999).map(|v| (v, v + 1)).collect()));
+        // A cycle with tails hanging off both ends.
+        let mut edges: Vec<(u32, u32)> = (0..10).map(|v| (v, (v + 1) % 10)).collect();
+        edges.extend(vec![(10, 0), (11, 10), (5, 12), (12, 13), (13, 13)]);
+        check_scc(&digraph(14, edges));
+        for seed in 0..5 {
+            check_scc(&digraph(300, random_edges(300, 450, seed)));
+        }
+    }
+
+    #[test]
+    fn afforest_matches_union_find() {
+        check_cc(&StaticGraph::from_edge_file(Path::new("testgraph.txt")));
+        check_cc(&graph(0, vec![]));
+        check_cc(&graph(50, vec![]));
+        // One giant component plus many small ones and isolated vertices,
+        // so that both the sampled and the skipped paths run.
+        let mut edges: Vec<(u32, u32)> = (1..3000).map(|v| (v / 2, v)).collect();
+        edges.extend((0..500).map(|i| (3000 + 3 * i, 3001 + 3 * i)));
+        check_cc(&graph(5000, edges));
+        // The giant component does not contain vertex 0.
+        check_cc(&graph(4000, (100..3999).map(|v| (v, v + 1)).collect()));
+        for seed in 0..5 {
+            check_cc(&graph(2000, random_edges(2000, 1500, seed)));
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::simplegraphs::SimpleVertex;
+use crate::traits::Graph;
+use crate::{StaticGraph, VOOR};
+
+/// The k-core decomposition of an undirected graph: `core[v]` is the
+/// largest `k` such that `v` belongs to a subgraph in which every vertex
+/// has degree at least `k`, `degeneracy` is the largest core number, and
+/// `order` lists the vertices in the order they were peeled off. Every
+/// vertex has at most `degeneracy` neighbors later in `order`.
+pub struct CoreDecomposition<V> {
+    pub core: Vec<usize>,
+    pub degeneracy: usize,
+    pub order: Vec<V>,
+}
+
+impl<V> CoreDecomposition<V>
+where
+    V: SimpleVertex,
+{
+    fn new(core: Vec<usize>, order: Vec<V>) -> Self {
+        let degeneracy = core.iter().cloned().max().unwrap_or(0);
+        CoreDecomposition { core, degeneracy, order }
+    }
+
+    /// The position of each vertex in `order`.
+    pub fn rank(&self) -> Vec<usize> {
+        let mut rank = vec![0usize; self.order.len()];
+        for (i, v) in self.order.iter().enumerate() {
+            rank[v.as_()] = i;
+        }
+        rank
+    }
+}
+
+/// The degree of `v` not counting self-loops, which never affect cores.
+fn loopless_degree<V>(g: &StaticGraph<V>, v: V) -> usize
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    g.out_neighbors(v).iter().filter(|u| **u != v).count()
+}
+
+/// Core numbers by the bucket-based peeling algorithm of Batagelj and
+/// Zaversnik (2003), in O(n + m) time.
+///
+/// Vertices are kept in an array sorted by current degree, with `bin[d]`
+/// the start of the block of degree `d`. Peeling a vertex moves each of its
+/// higher-degree neighbors to the front of its block and shrinks the block
+/// by one, which keeps the array sorted without any searching.
+pub fn core_decomposition<V>(g: &StaticGraph<V>) -> CoreDecomposition<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let n: usize = g.nv().as_();
+    let mut deg: Vec<usize> = g.vertices().map(|v| loopless_degree(g, v)).collect();
+    let max_deg = deg.iter().cloned().max().unwrap_or(0);
+
+    let mut bin = vec![0usize; max_deg + 1];
+    for d in deg.iter() {
+        bin[*d] += 1;
+    }
+    let mut start = 0;
+    for b in bin.iter_mut() {
+        let count = *b;
+        *b = start;
+        start += count;
+    }
+    let mut pos = vec![0usize; n];
+    let mut vert = vec![0usize; n];
+    for v in 0..n {
+        pos[v] = bin[deg[v]];
+        vert[pos[v]] = v;
+        bin[deg[v]] += 1;
+    }
+    for d in (1..=max_deg).rev() {
+        bin[d] = bin[d - 1];
+    }
+    if !bin.is_empty() {
+        bin[0] = 0;
+    }
+
+    for i in 0..n {
+        let v = vert[i];
+        for u in g.out_neighbors(V::from(v).expect(VOOR)) {
+            let u = u.as_();
+            if deg[u] > deg[v] {
+                let du = deg[u];
+                let pu = pos[u];
+                let pw = bin[du];
+                let w = vert[pw];
+                if u != w {
+                    pos[u] = pw;
+                    vert[pu] = w;
+                    pos[w] = pu;
+                    vert[pw] = u;
+                }
+                bin[du] += 1;
+                deg[u] -= 1;
+            }
+        }
+    }
+    let order = vert.into_iter().map(|v| V::from(v).expect(VOOR)).collect();
+    CoreDecomposition::new(deg, order)
+}
+
+const UNPEELED: usize = usize::MAX;
+
+/// Parallel version of `core_decomposition` by level-synchronous peeling.
+///
+/// For each level `k`, every remaining vertex of degree at most `k` is
+/// peeled at once; peeling decrements the degrees of remaining neighbors
+/// with atomics, and the neighbors that drop to `k` form the next frontier
+/// of the same level. `order` is a valid degeneracy order but generally
+/// differs from the sequential one.
+pub fn par_core_decomposition<V>(g: &StaticGraph<V>) -> CoreDecomposition<V>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let n: usize = g.nv().as_();
+    let deg: Vec<AtomicUsize> = (0..n)
+        .into_par_iter()
+        .map(|v| AtomicUsize::new(loopless_degree(g, V::from(v).expect(VOOR))))
+        .collect();
+    let core: Vec<AtomicUsize> = (0..n).map(|_| AtomicUsize::new(UNPEELED)).collect();
+    let mut order: Vec<V> = Vec::with_capacity(n);
+    let unpeeled = |v: usize| core[v].load(Ordering::Relaxed) == UNPEELED;
+
+    while order.len() < n {
+        // Skip straight to the lowest remaining degree.
+        let k = (0..n)
+            .into_par_iter()
+            .filter(|v| unpeeled(*v))
+            .map(|v| deg[v].load(Ordering::Relaxed))
+            .min()
+            .unwrap();
+        let mut frontier: Vec<usize> = (0..n)
+            .into_par_iter()
+            .filter(|v| unpeeled(*v) && deg[*v].load(Ordering::Relaxed) <= k)
+            .collect();
+        while !frontier.is_empty() {
+            // Mark the whole frontier first so that edges inside it are
+            // not counted against either endpoint.
+            frontier.par_iter().for_each(|v| core[*v].store(k, Ordering::Relaxed));
+            let next: Vec<usize> = frontier
+                .par_iter()
+                .flat_map(|v| g.out_neighbors(V::from(*v).expect(VOOR)).par_iter())
+                .filter_map(|u| {
+                    let u = u.as_();
+                    if unpeeled(u) && deg[u].fetch_sub(1, Ordering::Relaxed) == k + 1 {
+                        Some(u)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            order.extend(frontier.iter().map(|v| V::from(*v).expect(VOOR)));
+            frontier = next;
+        }
+    }
+    let core = core.into_iter().map(|c| c.into_inner()).collect();
+    CoreDecomposition::new(core, order)
+}
+
+/// The core numbers of `g`; shorthand for `core_decomposition(g).core`.
+pub fn core_numbers<V>(g: &StaticGraph<V>) -> Vec<usize>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    core_decomposition(g).core
+}
+
+/// The k-core of `g`: the largest induced subgraph in which every vertex
+/// has degree at least `k`, along with the original id of each of its
+/// vertices. Empty if `k` exceeds the degeneracy.
+pub fn k_core<V>(g: &StaticGraph<V>, k: usize) -> (StaticGraph<V>, Vec<V>)
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let keep: Vec<bool> = core_numbers(g).into_iter().map(|c| c >= k).collect();
+    g.induced_subgraph(&keep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{graph, random_edges};
+    use crate::traits::FromEdgeList;
+    use std::path::Path;
+
+    /// K4 on 0..4, a pendant 4 hanging off 0, and an isolated vertex 5;
+    /// 4 and 5 carry self-loops, which must not raise their cores.
+    fn k4_with_pendant() -> StaticGraph<u32> {
+        graph(6, vec![(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3), (0, 4), (4, 4), (5, 5)])
+    }
+
+    /// Core numbers by repeatedly deleting every vertex of degree below `k`.
+    fn naive_cores(g: &StaticGraph<u32>) -> Vec<usize> {
+        let n = g.nv() as usize;
+        let mut core = vec![0; n];
+        for k in 1..n {
+            let mut alive = vec![true; n];
+            loop {
+                let doomed: Vec<usize> = (0..n)
+                    .filter(|v| alive[*v])
+                    .filter(|v| {
+                        let deg = g.out_neighbors(*v as u32).iter().filter(|u| **u as usize != *v && alive[**u as usize]).count();
+                        deg < k
+                    })
+                    .collect();
+                if doomed.is_empty() {
+                    break;
+                }
+                doomed.into_iter().for_each(|v| alive[v] = false);
+            }
+            (0..n).filter(|v| alive[*v]).for_each(|v| core[v] = k);
+        }
+        core
+    }
+
+    /// `order` is a permutation in which every vertex has at most its own
+    /// core number, and so at most the degeneracy, of neighbors after it.
+    fn check_order(g: &StaticGraph<u32>, d: &CoreDecomposition<u32>) {
+        let mut sorted = d.order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, g.vertices().collect::<Vec<_>>());
+        let rank = d.rank();
+        for v in g.vertices() {
+            let later = g.out_neighbors(v).iter().filter(|u| rank[**u as usize] > rank[v as usize]).count();
+            assert!(later <= d.core[v as usize], "vertex {} has {} later neighbors", v, later);
+            assert!(later <= d.degeneracy);
+        }
+    }
+
+    fn check(g: &StaticGraph<u32>) {
+        let seq = core_decomposition(g);
+        let par = par_core_decomposition(g);
+        assert_eq!(seq.core, naive_cores(g));
+        assert_eq!(par.core, seq.core);
+        assert_eq!(par.degeneracy, seq.degeneracy);
+        check_order(g, &seq);
+        check_order(g, &par);
+    }
+
+    #[test]
+    fn cores_of_a_known_graph() {
+        let g = k4_with_pendant();
+        let d = core_decomposition(&g);
+        assert_eq!(d.core, vec![3, 3, 3, 3, 1, 0]);
+        assert_eq!(d.degeneracy, 3);
+        assert_eq!(core_numbers(&g), d.core);
+        check(&g);
+    }
+
+    #[test]
+    fn par_matches_sequential() {
+        check(&StaticGraph::from_edge_file(Path::new("testgraph.txt")));
+        check(&graph(0, vec![]));
+        check(&graph(10, vec![]));
+        for seed in 0..5 {
+            check(&graph(150, random_edges(150, 600, seed)));
+        }
+    }
+
+    #[test]
+    fn k_core_above_at_and_below_degeneracy() {
+        let g = k4_with_pendant();
+        let (h, ids) = k_core(&g, 4);
+        assert_eq!((h.nv(), h.ne()), (0, 0));
+        assert!(ids.is_empty());
+
+        let (h, ids) = k_core(&g, 3);
+        assert_eq!(ids, vec![0, 1, 2, 3]);
+        assert_eq!(h.ne(), 6);
+
+        let (h, ids) = k_core(&g, 1);
+        assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+        // The pendant's self-loop comes along with it.
+        assert_eq!(h.ne(), 8);
+        assert!(h.out_neighbors(4).contains(&4));
+
+        let (h, ids) = k_core(&g, 0);
+        assert_eq!(ids, g.vertices().collect::<Vec<_>>());
+        assert_eq!(h.ne(), g.ne());
+    }
+}
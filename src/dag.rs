@@ -0,0 +1,232 @@
+use crate::simplegraphs::SimpleVertex;
+use crate::traits::Graph;
+use crate::{csr_from_edges, StaticDiGraph};
+
+/// A directed cycle, as a list of vertices in which each vertex has an edge
+/// to the next and the last has an edge back to the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle<V>(pub Vec<V>);
+
+/// Topological sort by Kahn's algorithm. Returns the vertices in an order
+/// where every edge goes from an earlier vertex to a later one, or a cycle
+/// if the graph is not a DAG.
+pub fn topological_sort<V>(g: &StaticDiGraph<V>) -> Result<Vec<V>, Cycle<V>>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let n: usize = g.nv().as_();
+    let mut indeg: Vec<usize> = g.vertices().map(|v| g.in_degree(v).as_()).collect();
+    let mut order: Vec<V> = Vec::with_capacity(n);
+    order.extend(g.vertices().filter(|v| indeg[v.as_()] == 0));
+
+    // `order` doubles as the queue: everything past `i` is ready to emit.
+    let mut i = 0;
+    while i < order.len() {
+        let u = order[i];
+        for v in g.out_neighbors(u) {
+            let d = &mut indeg[v.as_()];
+            *d -= 1;
+            if *d == 0 {
+                order.push(*v);
+            }
+        }
+        i += 1;
+    }
+    if order.len() == n {
+        return Ok(order);
+    }
+
+    // Every vertex left over still has an in-neighbor that is also left
+    // over, so walking backwards along such edges must revisit a vertex.
+    let start = g.vertices().find(|v| indeg[v.as_()] > 0).unwrap();
+    let mut pos = vec![usize::MAX; n];
+    let mut walk: Vec<V> = vec![];
+    let mut v = start;
+    while pos[v.as_()] == usize::MAX {
+        pos[v.as_()] = walk.len();
+        walk.push(v);
+        v = *g.in_neighbors(v).iter().find(|u| indeg[u.as_()] > 0).unwrap();
+    }
+    let mut cycle = walk.split_off(pos[v.as_()]);
+    cycle.reverse();
+    Err(Cycle(cycle))
+}
+
+pub fn is_acyclic<V>(g: &StaticDiGraph<V>) -> bool
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    topological_sort(g).is_ok()
+}
+
+/// A longest path (by number of edges) in a DAG, or a cycle if the graph
+/// is not a DAG.
+pub fn longest_path<V>(g: &StaticDiGraph<V>) -> Result<Vec<V>, Cycle<V>>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let order = topological_sort(g)?;
+    let n: usize = g.nv().as_();
+    let mut len = vec![0usize; n];
+    let mut pred: Vec<Option<V>> = vec![None; n];
+    for u in order.iter() {
+        let lu = len[u.as_()];
+        for v in g.out_neighbors(*u) {
+            if lu + 1 > len[v.as_()] {
+                len[v.as_()] = lu + 1;
+                pred[v.as_()] = Some(*u);
+            }
+        }
+    }
+    let end = match g.vertices().max_by_key(|v| len[v.as_()]) {
+        Some(v) => v,
+        None => return Ok(vec![]),
+    };
+    let mut path = vec![end];
+    while let Some(p) = pred[path[path.len() - 1].as_()] {
+        path.push(p);
+    }
+    path.reverse();
+    Ok(path)
+}
+
+/// The transitive reduction of a DAG: the graph with the fewest edges that
+/// has the same reachability. Keeps every vertex of `g`. Returns a cycle if
+/// the graph is not a DAG.
+pub fn transitive_reduction<V>(g: &StaticDiGraph<V>) -> Result<StaticDiGraph<V>, Cycle<V>>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    topological_sort(g)?;
+    let n: usize = g.nv().as_();
+    // mark[w] == u means w is reachable from u by a path of 2 or more edges.
+    let mut mark = vec![usize::MAX; n];
+    let mut stack: Vec<V> = vec![];
+    let mut edges: Vec<(V, V)> = Vec::with_capacity(g.ne());
+    for u in g.vertices() {
+        let uu = u.as_();
+        for v in g.out_neighbors(u) {
+            for w in g.out_neighbors(*v) {
+                if mark[w.as_()] != uu {
+                    mark[w.as_()] = uu;
+                    stack.push(*w);
+                }
+            }
+        }
+        while let Some(x) = stack.pop() {
+            for w in g.out_neighbors(x) {
+                if mark[w.as_()] != uu {
+                    mark[w.as_()] = uu;
+                    stack.push(*w);
+                }
+            }
+        }
+        edges.extend(g.out_neighbors(u).iter().filter(|v| mark[v.as_()] != uu).map(|v| (u, *v)));
+    }
+    let (indptr, indices) = csr_from_edges(n, edges);
+    Ok(StaticDiGraph::from_csr(indptr, indices).expect("invalid CSR"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{digraph, random_edges};
+    use crate::traversals::bfs;
+
+    fn edges(g: &StaticDiGraph<u32>) -> Vec<(u32, u32)> {
+        g.vertices().flat_map(|u| g.out_neighbors(u).iter().map(move |v| (u, *v))).collect()
+    }
+
+    fn has_edge(g: &StaticDiGraph<u32>, u: u32, v: u32) -> bool {
+        g.out_neighbors(u).binary_search(&v).is_ok()
+    }
+
+    /// Every vertex of the cycle has an edge to the next, wrapping around.
+    fn check_cycle(g: &StaticDiGraph<u32>) {
+        let Cycle(c) = topological_sort(g).unwrap_err();
+        assert!(!c.is_empty());
+        for (i, u) in c.iter().enumerate() {
+            assert!(has_edge(g, *u, c[(i + 1) % c.len()]), "{:?} is not a cycle", c);
+        }
+        assert!(!is_acyclic(g));
+        assert!(longest_path(g).is_err());
+        assert!(transitive_reduction(g).is_err());
+    }
+
+    /// A random DAG: every edge goes from a lower to a higher vertex.
+    fn random_dag(n: u64, m: usize, seed: u64) -> StaticDiGraph<u32> {
+        let e = random_edges(n, m, seed).into_iter().filter(|(u, v)| u != v).map(|(u, v)| (u.min(v), u.max(v))).collect();
+        digraph(n as usize, e)
+    }
+
+    #[test]
+    fn topological_order_puts_edges_forward() {
+        for g in [random_dag(200, 600, 1), digraph(5, vec![]), digraph(0, vec![])].iter() {
+            let order = topological_sort(g).unwrap();
+            let mut rank = vec![0; order.len()];
+            order.iter().enumerate().for_each(|(i, v)| rank[*v as usize] = i);
+            assert_eq!(order.len(), g.nv() as usize);
+            assert!(edges(g).iter().all(|(u, v)| rank[*u as usize] < rank[*v as usize]));
+        }
+    }
+
+    #[test]
+    fn cycles_are_reported_with_a_witness() {
+        // A 3-cycle reachable from a tail, and a DAG part after it.
+        check_cycle(&digraph(6, vec![(0, 1), (1, 2), (2, 3), (3, 1), (3, 4), (4, 5)]));
+        // A self-loop is a cycle of one vertex.
+        let g = digraph(4, vec![(0, 1), (1, 2), (2, 2), (2, 3)]);
+        assert_eq!(topological_sort(&g), Err(Cycle(vec![2])));
+        check_cycle(&g);
+        check_cycle(&digraph(2, vec![(0, 1), (1, 0)]));
+        for seed in 0..5 {
+            check_cycle(&digraph(100, random_edges(100, 300, seed)));
+        }
+    }
+
+    #[test]
+    fn longest_path_of_a_known_dag() {
+        // 0 -> 1 -> 2 -> 3 -> 6 is the only path with four edges.
+        let g = digraph(8, vec![(0, 1), (1, 2), (2, 3), (3, 6), (0, 3), (4, 2), (5, 6), (1, 6)]);
+        assert_eq!(longest_path(&g), Ok(vec![0, 1, 2, 3, 6]));
+        assert_eq!(longest_path(&digraph(3, vec![])).unwrap().len(), 1);
+        assert_eq!(longest_path(&digraph(0, vec![])), Ok(vec![]));
+
+        let g = random_dag(200, 600, 2);
+        let path = longest_path(&g).unwrap();
+        assert!(path.windows(2).all(|e| has_edge(&g, e[0], e[1])));
+        // No path is longer: compare with a brute-force longest-path length.
+        let mut len = vec![0usize; 200];
+        for u in (0..200u32).rev() {
+            len[u as usize] = g.out_neighbors(u).iter().map(|v| len[*v as usize] + 1).max().unwrap_or(0);
+        }
+        assert_eq!(path.len() - 1, *len.iter().max().unwrap());
+    }
+
+    #[test]
+    fn transitive_reduction_drops_implied_edges() {
+        let g = digraph(5, vec![(0, 1), (1, 2), (0, 2), (2, 3), (0, 3), (1, 3), (4, 3)]);
+        let r = transitive_reduction(&g).unwrap();
+        assert_eq!(edges(&r), vec![(0, 1), (1, 2), (2, 3), (4, 3)]);
+        assert_eq!(r.nv(), 5);
+
+        // An edge u -> v is implied exactly when v is reachable from some
+        // other out-neighbor of u.
+        let g = random_dag(80, 300, 3);
+        let reach: Vec<Vec<u32>> = g.vertices().map(|v| bfs(&g, v)).collect();
+        let expected: Vec<(u32, u32)> = edges(&g)
+            .into_iter()
+            .filter(|(u, v)| !g.out_neighbors(*u).iter().any(|w| w != v && reach[*w as usize][*v as usize] != u32::MAX))
+            .collect();
+        let r = transitive_reduction(&g).unwrap();
+        assert_eq!(edges(&r), expected);
+        for v in g.vertices() {
+            let reachable = |l: Vec<u32>| l.into_iter().map(|x| x != u32::MAX).collect::<Vec<_>>();
+            assert_eq!(reachable(bfs(&r, v)), reachable(reach[v as usize].clone()));
+        }
+    }
+}
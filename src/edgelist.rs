@@ -0,0 +1,248 @@
+use std::io::BufRead;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use crate::errors::LoadError;
+use crate::simplegraphs::SimpleVertex;
+
+/// Parses a `# N vertices` header comment, as written by `write_edge_list`.
+fn parse_nv_header(comment: &str) -> Option<usize> {
+    let mut words = comment.trim_start_matches('#').split_whitespace();
+    let n = words.next()?.parse().ok()?;
+    if words.next()?.trim_end_matches(',') == "vertices" {
+        Some(n)
+    } else {
+        None
+    }
+}
+
+/// The non-comment lines of an edge list, split into fields.
+///
+/// Lines are read into one reused buffer and fields are handed out as
+/// slices of it, so parsing allocates nothing per line.
+struct Records<R> {
+    reader: R,
+    buf: String,
+    lineno: usize,
+    /// The vertex count and line number of the last `# N vertices` header.
+    nv: Option<(usize, usize)>,
+}
+
+/// The most fields any record has (`src dst weight`).
+const MAX_FIELDS: usize = 3;
+
+impl<R: BufRead> Records<R> {
+    fn new(reader: R) -> Self {
+        Records { reader, buf: String::new(), lineno: 0, nv: None }
+    }
+
+    /// Returns the next line that is neither blank nor a `#` comment, along
+    /// with its line number, checking that it has `expected` fields. Only
+    /// the first `expected` entries of the returned array are filled in.
+    fn next_record(&mut self, expected: usize) -> Option<Result<(usize, [&str; MAX_FIELDS]), LoadError>> {
+        loop {
+            self.buf.clear();
+            self.lineno += 1;
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(LoadError::Io { line: self.lineno, source: e })),
+            }
+            let l = self.buf.trim();
+            if l.is_empty() {
+                continue;
+            }
+            if l.starts_with('#') {
+                if let Some(n) = parse_nv_header(l) {
+                    self.nv = Some((n, self.lineno));
+                }
+                continue;
+            }
+            break;
+        }
+        let lineno = self.lineno;
+        let l = self.buf.trim();
+        let mut fields = [""; MAX_FIELDS];
+        let mut found = 0;
+        for f in l.split_whitespace() {
+            if found < MAX_FIELDS {
+                fields[found] = f;
+            }
+            found += 1;
+        }
+        if found != expected {
+            return Some(Err(LoadError::FieldCount {
+                line: lineno,
+                text: l.to_string(),
+                expected,
+                found,
+            }));
+        }
+        Some(Ok((lineno, fields)))
+    }
+
+    /// The vertex count of the last `# N vertices` header read so far. The
+    /// count itself must fit in `V`, since it is the end of `vertices()`.
+    fn declared_nv<V: SimpleVertex>(&self) -> Result<Option<usize>, LoadError> {
+        match self.nv {
+            None | Some((0, _)) => Ok(None),
+            Some((n, line)) => match V::from(n) {
+                Some(_) => Ok(Some(n)),
+                None => Err(LoadError::VertexOutOfRange { line, text: n.to_string() }),
+            },
+        }
+    }
+}
+
+/// An iterator over the `(src, dst)` pairs of a whitespace-separated edge
+/// list. Blank lines and lines starting with `#` are skipped, except that a
+/// `# N vertices` header is remembered; see `declared_nv`.
+///
+/// Edges are yielded as they are read, so this can be used to stream graphs
+/// that are too large to hold in memory.
+pub struct EdgeListReader<R, V> {
+    records: Records<R>,
+    _v: PhantomData<V>,
+}
+
+impl<R, V> EdgeListReader<R, V>
+where
+    R: BufRead,
+    V: SimpleVertex,
+{
+    pub fn new(reader: R) -> Self {
+        EdgeListReader { records: Records::new(reader), _v: PhantomData }
+    }
+
+    /// The vertex count declared by a `# N vertices` header comment, if one
+    /// has been read so far. This is how isolated vertices past the largest
+    /// vertex in any edge survive a `write_edge_list` round trip.
+    pub fn declared_nv(&self) -> Result<Option<usize>, LoadError> {
+        self.records.declared_nv::<V>()
+    }
+}
+
+impl<R, V> Iterator for EdgeListReader<R, V>
+where
+    R: BufRead,
+    V: SimpleVertex,
+{
+    type Item = Result<(V, V), LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (lineno, fields) = match self.records.next_record(2)? {
+            Ok(r) => r,
+            Err(e) => return Some(Err(e)),
+        };
+        let edge = parse_vertex(fields[0], lineno)
+            .and_then(|src| parse_vertex(fields[1], lineno).map(|dst| (src, dst)));
+        Some(edge)
+    }
+}
+
+/// An iterator over the `(src, dst, weight)` triples of a weighted edge
+/// list. Blank lines and lines starting with `#` are skipped, except that a
+/// `# N vertices` header is remembered; see `declared_nv`.
+pub struct WeightedEdgeListReader<R, V, W> {
+    records: Records<R>,
+    _v: PhantomData<(V, W)>,
+}
+
+impl<R, V, W> WeightedEdgeListReader<R, V, W>
+where
+    R: BufRead,
+    V: SimpleVertex,
+    W: FromStr,
+{
+    pub fn new(reader: R) -> Self {
+        WeightedEdgeListReader { records: Records::new(reader), _v: PhantomData }
+    }
+
+    /// The vertex count declared by a `# N vertices` header comment, if one
+    /// has been read so far; see `EdgeListReader::declared_nv`.
+    pub fn declared_nv(&self) -> Result<Option<usize>, LoadError> {
+        self.records.declared_nv::<V>()
+    }
+}
+
+impl<R, V, W> Iterator for WeightedEdgeListReader<R, V, W>
+where
+    R: BufRead,
+    V: SimpleVertex,
+    W: FromStr,
+{
+    type Item = Result<(V, V, W), LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (lineno, fields) = match self.records.next_record(3)? {
+            Ok(r) => r,
+            Err(e) => return Some(Err(e)),
+        };
+        let edge = parse_vertex(fields[0], lineno).and_then(|src| {
+            let dst = parse_vertex(fields[1], lineno)?;
+            let w: W = fields[2]
+                .parse()
+                .map_err(|_| LoadError::Parse { line: lineno, text: fields[2].to_string() })?;
+            Ok((src, dst, w))
+        });
+        Some(edge)
+    }
+}
+
+/// Reads an entire edge list into memory.
+pub fn read_edges<V, R>(reader: R) -> Result<Vec<(V, V)>, LoadError>
+where
+    R: BufRead,
+    V: SimpleVertex,
+{
+    EdgeListReader::new(reader).collect()
+}
+
+/// Reads an entire edge list into memory, along with its vertex count: the
+/// larger of the `# N vertices` header, if any, and one past the largest
+/// vertex in any edge.
+pub fn read_edges_nv<V, R>(reader: R) -> Result<(usize, Vec<(V, V)>), LoadError>
+where
+    R: BufRead,
+    V: SimpleVertex,
+{
+    let mut r = EdgeListReader::new(reader);
+    let edges: Vec<(V, V)> = (&mut r).collect::<Result<_, _>>()?;
+    let max = edges.iter().map(|e| e.0.max(e.1).as_() + 1).max().unwrap_or(0);
+    Ok((r.declared_nv()?.unwrap_or(0).max(max), edges))
+}
+
+/// Reads an entire weighted (`src dst weight`) edge list into memory.
+pub fn read_weighted_edges<V, W, R>(reader: R) -> Result<Vec<(V, V, W)>, LoadError>
+where
+    R: BufRead,
+    V: SimpleVertex,
+    W: FromStr,
+{
+    WeightedEdgeListReader::new(reader).collect()
+}
+
+/// A vertex count and the weighted edges of a graph on that many vertices.
+type WeightedEdgeList<V, W> = (usize, Vec<(V, V, W)>);
+
+/// Reads an entire weighted edge list into memory, along with its vertex
+/// count; see `read_edges_nv`.
+pub fn read_weighted_edges_nv<V, W, R>(reader: R) -> Result<WeightedEdgeList<V, W>, LoadError>
+where
+    R: BufRead,
+    V: SimpleVertex,
+    W: FromStr,
+{
+    let mut r = WeightedEdgeListReader::new(reader);
+    let edges: Vec<(V, V, W)> = (&mut r).collect::<Result<_, _>>()?;
+    let max = edges.iter().map(|e| e.0.max(e.1).as_() + 1).max().unwrap_or(0);
+    Ok((r.declared_nv()?.unwrap_or(0).max(max), edges))
+}
+
+pub(crate) fn parse_vertex<V>(field: &str, lineno: usize) -> Result<V, LoadError>
+where
+    V: SimpleVertex,
+{
+    let x: u128 = field.parse().map_err(|_| LoadError::Parse { line: lineno, text: field.to_string() })?;
+    V::from(x).ok_or_else(|| LoadError::VertexOutOfRange { line: lineno, text: field.to_string() })
+}
@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors produced while loading a graph from an edge list.
+///
+/// Line numbers are 1-based; `line` is 0 when the error happened before any
+/// line was read (e.g., the file could not be opened).
+#[derive(Debug)]
+pub enum LoadError {
+    /// The underlying reader failed.
+    Io { line: usize, source: io::Error },
+    /// A field could not be parsed as a non-negative integer.
+    Parse { line: usize, text: String },
+    /// A field parsed, but does not fit in the vertex type `V`.
+    VertexOutOfRange { line: usize, text: String },
+    /// A line had the wrong number of fields.
+    FieldCount { line: usize, text: String, expected: usize, found: usize },
+    /// The input is not in the expected format (bad header, truncated data, etc.).
+    Format { line: usize, text: String },
+}
+
+impl LoadError {
+    /// The line on which the error occurred.
+    pub fn line(&self) -> usize {
+        match self {
+            LoadError::Io { line, .. } => *line,
+            LoadError::Parse { line, .. } => *line,
+            LoadError::VertexOutOfRange { line, .. } => *line,
+            LoadError::FieldCount { line, .. } => *line,
+            LoadError::Format { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io { line, source } => write!(f, "line {}: I/O error: {}", line, source),
+            LoadError::Parse { line, text } => write!(f, "line {}: cannot parse {:?}", line, text),
+            LoadError::VertexOutOfRange { line, text } => {
+                write!(f, "line {}: vertex out of range: {:?}", line, text)
+            }
+            LoadError::FieldCount { line, text, expected, found } => write!(
+                f,
+                "line {}: expected {} fields, found {}: {:?}",
+                line, expected, found, text
+            ),
+            LoadError::Format { line, text } => write!(f, "line {}: invalid format: {}", line, text),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Errors produced when building a graph from raw CSR arrays.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsrError {
+    /// `indptr` is empty, does not start at 0, or does not end at `indices.len()`.
+    InvalidIndptr,
+    /// `indptr[row + 1] < indptr[row]`.
+    DecreasingIndptr { row: usize },
+    /// The neighbors of `row` are not sorted in ascending order.
+    UnsortedRow { row: usize },
+    /// `row` has a neighbor `index` that is not a vertex of the graph.
+    IndexOutOfRange { row: usize, index: usize },
+    /// An undirected graph has the edge `src -> dst` but not `dst -> src`.
+    Asymmetric { src: usize, dst: usize },
+}
+
+impl fmt::Display for CsrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsrError::InvalidIndptr => write!(f, "indptr must start at 0 and end at indices.len()"),
+            CsrError::DecreasingIndptr { row } => write!(f, "indptr decreases at row {}", row),
+            CsrError::UnsortedRow { row } => write!(f, "row {} is not sorted", row),
+            CsrError::IndexOutOfRange { row, index } => {
+                write!(f, "row {}: index {} out of range", row, index)
+            }
+            CsrError::Asymmetric { src, dst } => {
+                write!(f, "edge {} -> {} has no reverse edge", src, dst)
+            }
+        }
+    }
+}
+
+impl Error for CsrError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::FromEdgeList;
+    use crate::{StaticDiGraph, StaticGraph};
+    use std::path::Path;
+
+    fn load_error(text: &str) -> LoadError {
+        let err = StaticGraph::<u8>::try_from_edge_reader(text.as_bytes()).err().expect("expected an error");
+        // Both graph types share the parser and must agree.
+        let di = StaticDiGraph::<u8>::try_from_edge_reader(text.as_bytes()).err().expect("expected an error");
+        assert_eq!(err.to_string(), di.to_string());
+        err
+    }
+
+    #[test]
+    fn unparsable_field() {
+        let err = load_error("0 1\n0 x\n");
+        assert!(matches!(err, LoadError::Parse { line: 2, ref text } if text == "x"), "{:?}", err);
+        let err = load_error("-1 0\n");
+        assert!(matches!(err, LoadError::Parse { line: 1, ref text } if text == "-1"), "{:?}", err);
+        assert_eq!(err.to_string(), "line 1: cannot parse \"-1\"");
+    }
+
+    #[test]
+    fn vertex_out_of_range() {
+        let err = load_error("0 255\n0 256\n");
+        assert!(matches!(err, LoadError::VertexOutOfRange { line: 2, ref text } if text == "256"), "{:?}", err);
+        assert_eq!(err.line(), 2);
+    }
+
+    #[test]
+    fn wrong_field_count() {
+        let err = load_error("0 1 2\n");
+        assert!(
+            matches!(err, LoadError::FieldCount { line: 1, ref text, expected: 2, found: 3 } if text == "0 1 2"),
+            "{:?}",
+            err
+        );
+        let err = load_error("0 1\n3\n");
+        assert!(
+            matches!(err, LoadError::FieldCount { line: 2, ref text, expected: 2, found: 1 } if text == "3"),
+            "{:?}",
+            err
+        );
+        assert_eq!(err.to_string(), "line 2: expected 2 fields, found 1: \"3\"");
+    }
+
+    #[test]
+    fn line_numbers_count_blank_and_comment_lines() {
+        let err = load_error("# a comment\n\n0 1\n   \n# another\n1 y\n");
+        assert!(matches!(err, LoadError::Parse { line: 6, ref text } if text == "y"), "{:?}", err);
+        let err = load_error("\n\n\n7 8 9\n");
+        assert_eq!(err.line(), 4);
+    }
+
+    #[test]
+    fn missing_file() {
+        let err = StaticGraph::<u32>::try_from_edge_file(Path::new("no/such/graph.txt")).err().expect("expected an error");
+        match err {
+            LoadError::Io { line, ref source } => {
+                assert_eq!(line, 0);
+                assert_eq!(source.kind(), io::ErrorKind::NotFound);
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+        assert!(err.source().is_some());
+    }
+}
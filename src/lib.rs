@@ -1,23 +1,184 @@
-use num::cast::NumCast;
-use crate::traits::Graph;
+use crate::traits::{DirectedGraph, Graph, UndirectedGraph};
 use crate::simplegraphs::{SimpleVertex, SimpleEdge};
 use std::fmt;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
-use std::path::Path;
+use std::io::{BufRead, Write};
+use crate::errors::{CsrError, LoadError};
+pub mod approx;
+pub mod binary;
+pub mod components;
+pub mod cores;
+pub mod dag;
+pub mod edgelist;
+pub mod errors;
+pub mod mtx;
 pub mod traits;
 pub mod traversals;
+pub mod triads;
+pub mod triangles;
+pub mod truss;
+pub mod weighted;
 pub mod simplegraphs;
+#[cfg(test)]
+pub(crate) mod test_util;
 
 const VOOR: &str = "vertex out of range";
 
+/// Checks that `indptr`/`indices` describe a valid CSR matrix with
+/// sorted rows and in-range indices.
+fn validate_csr<V>(indptr: &[usize], indices: &[V]) -> Result<(), CsrError>
+where
+    V: SimpleVertex,
+{
+    if indptr.is_empty() || indptr[0] != 0 || indptr[indptr.len() - 1] != indices.len() {
+        return Err(CsrError::InvalidIndptr);
+    }
+    let n = indptr.len() - 1;
+    for row in 0..n {
+        if indptr[row + 1] < indptr[row] {
+            return Err(CsrError::DecreasingIndptr { row });
+        }
+    }
+    for row in 0..n {
+        let r = &indices[indptr[row]..indptr[row + 1]];
+        if let Some(v) = r.iter().find(|v| v.as_() >= n) {
+            return Err(CsrError::IndexOutOfRange { row, index: v.as_() });
+        }
+        if r.windows(2).any(|w| w[0] > w[1]) {
+            return Err(CsrError::UnsortedRow { row });
+        }
+    }
+    Ok(())
+}
+
+/// Allocates the zeroed row offsets of a CSR matrix with `n` rows. `n` may
+/// come from a file header, so a count too large to allocate is an error
+/// rather than an abort.
+pub(crate) fn try_indptr(n: usize) -> Result<Vec<usize>, LoadError> {
+    let too_large = || LoadError::Format { line: 0, text: format!("cannot allocate {} vertices", n) };
+    let len = n.checked_add(1).ok_or_else(too_large)?;
+    let mut indptr = vec![];
+    indptr.try_reserve_exact(len).map_err(|_| too_large())?;
+    indptr.resize(len, 0);
+    Ok(indptr)
+}
+
+/// Builds a CSR matrix with exactly `n` rows from `(row, col)` pairs,
+/// sorting each row and dropping duplicate entries.
+pub(crate) fn csr_from_edges<V>(n: usize, edges: Vec<(V, V)>) -> (Vec<usize>, Vec<V>)
+where
+    V: SimpleVertex,
+{
+    try_csr_from_edges(n, edges).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like `csr_from_edges`, but fails if `n` rows cannot be allocated.
+pub(crate) fn try_csr_from_edges<V>(n: usize, mut edges: Vec<(V, V)>) -> Result<(Vec<usize>, Vec<V>), LoadError>
+where
+    V: SimpleVertex,
+{
+    let mut indptr = try_indptr(n)?;
+    edges.sort_unstable();
+    edges.dedup();
+    for (s, _) in edges.iter() {
+        indptr[s.as_() + 1] += 1;
+    }
+    for i in 0..n {
+        indptr[i + 1] += indptr[i];
+    }
+    let indices = edges.into_iter().map(|(_, d)| d).collect();
+    Ok((indptr, indices))
+}
+
+/// Builds the transpose of a validated CSR matrix. Rows of the result are
+/// sorted because source rows are visited in order.
+fn transpose_csr<V>(indptr: &[usize], indices: &[V]) -> (Vec<usize>, Vec<V>)
+where
+    V: SimpleVertex,
+{
+    let n = indptr.len() - 1;
+    let mut tptr = vec![0usize; n + 1];
+    for v in indices {
+        tptr[v.as_() + 1] += 1;
+    }
+    for i in 0..n {
+        tptr[i + 1] += tptr[i];
+    }
+    let mut next = tptr.clone();
+    let mut tind = vec![V::zero(); indices.len()];
+    for row in 0..n {
+        let u = V::from(row).expect(VOOR);
+        for v in &indices[indptr[row]..indptr[row + 1]] {
+            let slot = &mut next[v.as_()];
+            tind[*slot] = u;
+            *slot += 1;
+        }
+    }
+    (tptr, tind)
+}
+
 pub struct StaticDiGraph<V>
 {
     fadj: graph_matrix::GraphMatrix<V>,
     badj: graph_matrix::GraphMatrix<V>,
 }
 
+impl<V> StaticDiGraph<V>
+where
+    V: SimpleVertex,
+{
+    /// Builds a directed graph from `(src, dst)` pairs.
+    pub fn from_edges<I>(edges: I) -> Self
+    where
+        I: IntoIterator<Item = (V, V)>,
+    {
+        let edges: Vec<(V, V)> = edges.into_iter().collect();
+        let bedges = edges.iter().map(|x| (x.1, x.0)).collect();
+        let fadj = graph_matrix::GraphMatrix::from_edges(edges);
+        let badj = graph_matrix::GraphMatrix::from_edges(bedges);
+        StaticDiGraph { fadj, badj }
+    }
+
+    /// Builds a directed graph from the CSR representation of its forward
+    /// adjacency. Each row of `indices` must be sorted, and every index must
+    /// be less than `indptr.len() - 1`.
+    pub fn from_csr(indptr: Vec<usize>, indices: Vec<V>) -> Result<Self, CsrError> {
+        validate_csr(&indptr, &indices)?;
+        let (bptr, bind) = transpose_csr(&indptr, &indices);
+        let fadj = graph_matrix::GraphMatrix::new(indptr, indices);
+        let badj = graph_matrix::GraphMatrix::new(bptr, bind);
+        Ok(StaticDiGraph { fadj, badj })
+    }
+
+    /// The underlying undirected graph, with an edge `{u, v}` wherever
+    /// this graph has `u -> v` or `v -> u`. Keeps every vertex.
+    pub fn to_undirected(&self) -> StaticGraph<V> {
+        let n = self.fadj.dim();
+        let mut edges: Vec<(V, V)> = Vec::with_capacity(2 * self.fadj.n());
+        for u in 0..n {
+            let uu = V::from(u).expect(VOOR);
+            for v in self.fadj.row(uu) {
+                edges.push((uu, *v));
+                edges.push((*v, uu));
+            }
+        }
+        let (indptr, indices) = csr_from_edges(n, edges);
+        StaticGraph::from_csr(indptr, indices).expect("invalid CSR")
+    }
+
+    /// Writes the graph as a `src dst` edge list readable by `from_edge_reader`.
+    /// The `# N vertices` header keeps trailing isolated vertices on reload.
+    pub fn write_edge_list<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        writeln!(w, "# {} vertices, {} edges", self.fadj.dim(), self.fadj.n())?;
+        for u in 0..self.fadj.dim() {
+            let uu = V::from(u).expect(VOOR);
+            for v in self.fadj.row(uu) {
+                writeln!(w, "{} {}", uu, v)?;
+            }
+        }
+        w.flush()
+    }
+}
+
 impl<V> traits::Graph<V, SimpleEdge<V>> for StaticDiGraph<V>
 // we need V to be a SimpleVertex, and we also need Range<V> to return an iterator over V.
 where
@@ -69,36 +230,27 @@ where
     fn out_neighbors(&self, v: V) -> &[V] {
         self.fadj.row(v)
     }
+}
 
-    fn from_edge_file(fname: &Path) -> Self {
-        let f = File::open(fname).expect("Cannot open file");
-        let file = BufReader::new(&f);
-        let mut edgelist: Vec<(V, V)> = vec![];
-        for line in file.lines() {
-            let l = line.expect("error reading file"); // produces a std::string::String
-            let l = l.trim(); // changes to &str
-            if l.starts_with("#") {
-                continue;
-            }
-            let mut eit = l.split_whitespace();
-            let s1 = eit.next().expect("Invalid line (first field)");
-            let s2 = eit.next().expect("Invalid line (second field)");
-            if eit.next().is_some() {
-                panic!("Invalid line (extra fields)");
-            }
-            let src128: u128 = s1.parse().unwrap();
-            let dst128: u128 = s2.parse().unwrap();
-            let src = V::from(src128).expect(VOOR);
-            let dst = V::from(dst128).expect(VOOR);
-            edgelist.push((src, dst));
-        }
-        let bedges = edgelist.clone().iter().map(|x| (x.1, x.0)).collect();
-        let fadj = graph_matrix::GraphMatrix::from_edges(edgelist);
-        let badj = graph_matrix::GraphMatrix::from_edges(bedges);
-        StaticDiGraph { fadj, badj }
+impl<V> traits::FromEdgeList for StaticDiGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    fn try_from_edge_reader<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        let (nv, edges) = edgelist::read_edges_nv(reader)?;
+        let (indptr, indices) = try_csr_from_edges(nv, edges)?;
+        Ok(StaticDiGraph::from_csr(indptr, indices).expect("invalid CSR"))
     }
 }
 
+impl<V> DirectedGraph<V, SimpleEdge<V>> for StaticDiGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+}
+
 impl<V> fmt::Display for StaticDiGraph<V>
 where
     V: SimpleVertex,
@@ -112,6 +264,177 @@ where
 pub struct StaticGraph<V>
 {
     adj: graph_matrix::GraphMatrix<V>,
+    ne: usize,
+}
+
+/// The number of edges of a symmetric adjacency matrix. Every edge is
+/// stored in both directions except self-loops, which are stored once.
+fn undirected_ne<V>(adj: &graph_matrix::GraphMatrix<V>) -> usize
+where
+    V: SimpleVertex,
+{
+    let loops = (0..adj.dim())
+        .filter(|u| {
+            let uu = V::from(*u).expect(VOOR);
+            adj.has_index(uu, uu)
+        })
+        .count();
+    (adj.n() + loops) / 2
+}
+
+impl<V> StaticGraph<V>
+where
+    V: SimpleVertex,
+{
+    /// Builds an undirected graph from `(src, dst)` pairs. Each edge only
+    /// needs to be given once, in either direction.
+    pub fn from_edges<I>(edges: I) -> Self
+    where
+        I: IntoIterator<Item = (V, V)>,
+    {
+        let mut edges: Vec<(V, V)> = edges.into_iter().collect();
+        let redges: Vec<(V, V)> = edges.iter().map(|x| (x.1, x.0)).collect();
+        edges.extend(redges);
+        let adj = graph_matrix::GraphMatrix::from_edges(edges);
+        let ne = undirected_ne(&adj);
+        StaticGraph { adj, ne }
+    }
+
+    /// Builds an undirected graph from a symmetric CSR adjacency matrix.
+    /// Each row of `indices` must be sorted, every index must be less than
+    /// `indptr.len() - 1`, and every edge must appear in both directions.
+    pub fn from_csr(indptr: Vec<usize>, indices: Vec<V>) -> Result<Self, CsrError> {
+        validate_csr(&indptr, &indices)?;
+        for row in 0..indptr.len() - 1 {
+            for v in &indices[indptr[row]..indptr[row + 1]] {
+                let vu = v.as_();
+                let vrow = &indices[indptr[vu]..indptr[vu + 1]];
+                let u = V::from(row).expect(VOOR);
+                if vrow.binary_search(&u).is_err() {
+                    return Err(CsrError::Asymmetric { src: row, dst: vu });
+                }
+            }
+        }
+        let adj = graph_matrix::GraphMatrix::new(indptr, indices);
+        let ne = undirected_ne(&adj);
+        Ok(StaticGraph { adj, ne })
+    }
+
+    /// The subgraph induced by the vertices `v` for which `keep[v]` is true,
+    /// relabeled to `0..k` in increasing order. Also returns, for each new
+    /// vertex, the vertex of `self` it came from.
+    pub fn induced_subgraph(&self, keep: &[bool]) -> (StaticGraph<V>, Vec<V>) {
+        let n = self.adj.dim();
+        let mut new_ids = vec![usize::MAX; n];
+        let mut old_ids: Vec<V> = vec![];
+        for u in 0..n {
+            if keep[u] {
+                new_ids[u] = old_ids.len();
+                old_ids.push(V::from(u).expect(VOOR));
+            }
+        }
+        let mut indptr: Vec<usize> = Vec::with_capacity(old_ids.len() + 1);
+        let mut indices: Vec<V> = vec![];
+        indptr.push(0);
+        for u in old_ids.iter() {
+            for v in self.adj.row(*u) {
+                let id = new_ids[v.as_()];
+                if id != usize::MAX {
+                    indices.push(V::from(id).expect(VOOR));
+                }
+            }
+            indptr.push(indices.len());
+        }
+        let g = StaticGraph::from_csr(indptr, indices).expect("invalid CSR");
+        (g, old_ids)
+    }
+
+    /// Stable ids for the edges of this graph. See `EdgeIndex`.
+    pub fn edge_index(&self) -> EdgeIndex<'_, V> {
+        let n = self.adj.dim();
+        let mut first: Vec<usize> = Vec::with_capacity(n);
+        let mut offsets: Vec<usize> = Vec::with_capacity(n + 1);
+        offsets.push(0);
+        for u in 0..n {
+            let uu = V::from(u).expect(VOOR);
+            let row = self.adj.row(uu);
+            let f = match row.binary_search(&uu) {
+                Ok(i) | Err(i) => i,
+            };
+            first.push(f);
+            offsets.push(offsets[u] + row.len() - f);
+        }
+        EdgeIndex { g: self, first, offsets }
+    }
+
+    /// Writes the graph as a `src dst` edge list readable by `from_edge_reader`.
+    /// Each undirected edge is written once, with `src <= dst`. The
+    /// `# N vertices` header keeps trailing isolated vertices on reload.
+    pub fn write_edge_list<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        writeln!(w, "# {} vertices, {} edges", self.adj.dim(), self.ne)?;
+        for u in 0..self.adj.dim() {
+            let uu = V::from(u).expect(VOOR);
+            for v in self.adj.row(uu).iter().filter(|v| **v >= uu) {
+                writeln!(w, "{} {}", uu, v)?;
+            }
+        }
+        w.flush()
+    }
+}
+
+/// Stable ids `0..len()` for the edges of a `StaticGraph`.
+///
+/// Each undirected edge `{u, v}` is numbered once, as `(u, v)` with
+/// `u <= v`, in the order its `v` appears in the CSR row of `u`. Ids depend
+/// only on the graph's adjacency, so they are the same on every run and
+/// match the order in which `write_edge_list` writes the edges.
+pub struct EdgeIndex<'a, V> {
+    g: &'a StaticGraph<V>,
+    /// `first[u]` is the position in row `u` of its first neighbor `>= u`.
+    first: Vec<usize>,
+    /// `offsets[u]` is the id of the first edge in row `u`.
+    offsets: Vec<usize>,
+}
+
+impl<'a, V> EdgeIndex<'a, V>
+where
+    V: SimpleVertex,
+{
+    /// The number of edges, counting each self-loop once.
+    pub fn len(&self) -> usize {
+        self.offsets[self.offsets.len() - 1]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The id of the edge `{u, v}`, or `None` if there is no such edge.
+    pub fn id(&self, u: V, v: V) -> Option<usize> {
+        let (u, v) = if u <= v { (u, v) } else { (v, u) };
+        let row = &self.g.adj.row(u)[self.first[u.as_()]..];
+        row.binary_search(&v).ok().map(|i| self.offsets[u.as_()] + i)
+    }
+
+    /// The endpoints `(u, v)`, with `u <= v`, of the edge with id `id`.
+    pub fn endpoints(&self, id: usize) -> (V, V) {
+        // The edge lives in the first row that ends past `id`.
+        let u = match self.offsets[1..].binary_search_by(|o| {
+            if *o <= id { std::cmp::Ordering::Less } else { std::cmp::Ordering::Greater }
+        }) {
+            Ok(i) | Err(i) => i,
+        };
+        let uu = V::from(u).expect(VOOR);
+        (uu, self.g.adj.row(uu)[self.first[u] + id - self.offsets[u]])
+    }
+
+    /// The endpoints of every edge, in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (V, V)> + '_ {
+        (0..self.first.len()).flat_map(move |u| {
+            let uu = V::from(u).expect(VOOR);
+            self.g.adj.row(uu)[self.first[u]..].iter().map(move |v| (uu, *v))
+        })
+    }
 }
 
 impl<V> traits::Graph<V, SimpleEdge<V>> for StaticGraph<V>
@@ -120,12 +443,12 @@ where
     std::ops::Range<V>: Iterator<Item=V>
 {
     type VIT = std::ops::Range<V>;
-    fn nv(&self) -> usize {
-        usize::from(self.adj.dim())
+    fn nv(&self) -> V::T {
+        V::T::from(self.adj.dim()).unwrap()
     }
 
     fn ne(&self) -> usize {
-        self.adj.n() / 2
+        self.ne
     }
 
     fn vertices(&self) -> std::ops::Range<V> {
@@ -163,35 +486,29 @@ where
     fn in_neighbors(&self, v: V) -> &[V] {
         self.out_neighbors(v)
     }
+}
 
-    fn from_edge_file(fname: &Path) -> Self {
-        let f = File::open(fname).expect("Cannot open file");
-        let file = BufReader::new(&f);
-        let mut edgelist: Vec<(V, V)> = vec![];
-        for line in file.lines() {
-            let l = line.expect("error reading file"); // produces a std::string::String
-            let l = l.trim(); // changes to &str
-            if l.starts_with("#") {
-                continue;
-            }
-            let mut eit = l.split_whitespace();
-            let s1 = eit.next().expect("Invalid line (first field)");
-            let s2 = eit.next().expect("Invalid line (second field)");
-            if eit.next().is_some() {
-                panic!("Invalid line (extra fields)");
-            }
-            let src128: u128 = s1.parse().unwrap();
-            let dst128: u128 = s2.parse().unwrap();
-            let src = V::from(src128).expect(VOOR);
-            let dst = V::from(dst128).expect(VOOR);
-            edgelist.push((src, dst));
-            edgelist.push((dst, src));
-        }
-        let adj = graph_matrix::GraphMatrix::from_edges(edgelist);
-        StaticGraph { adj }
+impl<V> traits::FromEdgeList for StaticGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    fn try_from_edge_reader<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        let (nv, mut edges) = edgelist::read_edges_nv(reader)?;
+        let redges: Vec<(V, V)> = edges.iter().map(|x| (x.1, x.0)).collect();
+        edges.extend(redges);
+        let (indptr, indices) = try_csr_from_edges(nv, edges)?;
+        Ok(StaticGraph::from_csr(indptr, indices).expect("invalid CSR"))
     }
 }
 
+impl<V> UndirectedGraph<V, SimpleEdge<V>> for StaticGraph<V>
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+}
+
 impl<V> fmt::Display for StaticGraph<V>
 where
     V: SimpleVertex,
@@ -202,3 +519,106 @@ where
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::FromEdgeList;
+    use std::path::Path;
+
+    fn same_graph<G: Graph<u32, SimpleEdge<u32>>>(a: &G, b: &G) {
+        assert_eq!(a.nv(), b.nv());
+        assert_eq!(a.ne(), b.ne());
+        for v in a.vertices() {
+            assert_eq!(a.out_neighbors(v), b.out_neighbors(v));
+            assert_eq!(a.in_neighbors(v), b.in_neighbors(v));
+        }
+    }
+
+    #[test]
+    fn edge_list_round_trip() {
+        let g = StaticGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        let mut buf: Vec<u8> = vec![];
+        g.write_edge_list(&mut buf).unwrap();
+        same_graph(&g, &StaticGraph::from_edge_reader(&buf[..]));
+
+        let g = StaticDiGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        let mut buf: Vec<u8> = vec![];
+        g.write_edge_list(&mut buf).unwrap();
+        same_graph(&g, &StaticDiGraph::from_edge_reader(&buf[..]));
+    }
+
+    #[test]
+    fn edge_list_keeps_isolated_vertices() {
+        let (indptr, indices) = csr_from_edges(6, vec![(0u32, 1u32), (1, 0), (1, 2), (2, 1)]);
+        let g = StaticGraph::from_csr(indptr, indices).unwrap();
+        let mut buf: Vec<u8> = vec![];
+        g.write_edge_list(&mut buf).unwrap();
+        let h = StaticGraph::<u32>::from_edge_reader(&buf[..]);
+        assert_eq!(h.nv(), 6);
+        same_graph(&g, &h);
+
+        let (indptr, indices) = csr_from_edges(5, vec![(0u32, 1u32), (1, 2)]);
+        let g = StaticDiGraph::from_csr(indptr, indices).unwrap();
+        let mut buf: Vec<u8> = vec![];
+        g.write_edge_list(&mut buf).unwrap();
+        let h = StaticDiGraph::<u32>::from_edge_reader(&buf[..]);
+        assert_eq!(h.nv(), 5);
+        same_graph(&g, &h);
+    }
+
+    #[test]
+    fn self_loops_count_once() {
+        let g = StaticGraph::<u32>::from_edge_reader("0 0\n0 1\n1 0\n2 2\n".as_bytes());
+        assert_eq!(g.ne(), 3);
+        assert_eq!(StaticGraph::from_edges(vec![(0u32, 0u32), (0, 1), (2, 2)]).ne(), 3);
+        let mut buf: Vec<u8> = vec![];
+        g.write_edge_list(&mut buf).unwrap();
+        assert!(buf.starts_with(b"# 3 vertices, 3 edges\n"));
+        same_graph(&g, &StaticGraph::from_edge_reader(&buf[..]));
+    }
+
+    #[test]
+    fn vertex_header_must_fit() {
+        let g = StaticGraph::<u8>::from_edge_reader("# 255 vertices\n0 1\n".as_bytes());
+        assert_eq!(g.nv(), 255);
+        let err = StaticGraph::<u8>::try_from_edge_reader("# 256 vertices\n0 1\n".as_bytes()).err().unwrap();
+        assert!(matches!(err, LoadError::VertexOutOfRange { line: 1, ref text } if text == "256"));
+        // Fits the vertex type, but the row offsets can never be allocated.
+        let err = StaticDiGraph::<u64>::try_from_edge_reader("# 2305843009213693952 vertices\n".as_bytes()).err().unwrap();
+        assert!(matches!(err, LoadError::Format { line: 0, .. }), "{}", err);
+    }
+
+    #[test]
+    fn from_csr_rejects_invalid_input() {
+        fn di(indptr: Vec<usize>, indices: Vec<u32>) -> Option<CsrError> {
+            StaticDiGraph::from_csr(indptr, indices).err()
+        }
+        fn un(indptr: Vec<usize>, indices: Vec<u32>) -> Option<CsrError> {
+            StaticGraph::from_csr(indptr, indices).err()
+        }
+        for check in [di, un].iter() {
+            assert_eq!(check(vec![], vec![]), Some(CsrError::InvalidIndptr));
+            assert_eq!(check(vec![1, 1], vec![0]), Some(CsrError::InvalidIndptr));
+            assert_eq!(check(vec![0, 1], vec![0, 0]), Some(CsrError::InvalidIndptr));
+            assert_eq!(check(vec![0, 2, 1, 2], vec![1, 2]), Some(CsrError::DecreasingIndptr { row: 1 }));
+            assert_eq!(check(vec![0, 0, 2], vec![1, 0]), Some(CsrError::UnsortedRow { row: 1 }));
+            assert_eq!(check(vec![0, 1, 1], vec![5]), Some(CsrError::IndexOutOfRange { row: 0, index: 5 }));
+            assert_eq!(check(vec![0], vec![]), None);
+        }
+        assert_eq!(di(vec![0, 1, 1], vec![1]), None);
+        let err = un(vec![0, 1, 3, 3], vec![1, 0, 2]).unwrap();
+        assert_eq!(err, CsrError::Asymmetric { src: 1, dst: 2 });
+        assert_eq!(err.to_string(), "edge 1 -> 2 has no reverse edge");
+    }
+
+    #[test]
+    fn from_edges_agrees_with_from_csr() {
+        for seed in 0..3 {
+            // Repeated edges and self-loops included.
+            let edges = test_util::random_edges(100, 300, seed);
+            let n = edges.iter().map(|(u, v)| *u.max(v)).max().unwrap() as usize + 1;
+            same_graph(&StaticDiGraph::from_edges(edges.clone()), &test_util::digraph(n, edges.clone()));
+            same_graph(&StaticGraph::from_edges(edges.clone()), &test_util::graph(n, edges));
+        }
+    }
+}
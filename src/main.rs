@@ -1,5 +1,7 @@
-use rustgraphs::traits::Graph;
-use rustgraphs::{StaticDiGraph, StaticGraph, triangles, threaded_triangles, threaded_triangles_csr, bfs, dijkstra};
+use rustgraphs_generic::traits::{FromEdgeList, Graph};
+use rustgraphs_generic::traversals::{bfs, dijkstra, direction_optimizing_bfs, par_bfs, Bfs};
+use rustgraphs_generic::triangles::{threaded_triangles, threaded_triangles_csr, threaded_triangles_with, triangles, Kernel, Parallelism};
+use rustgraphs_generic::{StaticDiGraph, StaticGraph};
 use std::env;
 use std::path::Path;
 use std::time::Instant;
@@ -22,11 +24,10 @@ fn main() {
         println!("Load took {}ms", now.elapsed().as_micros() as f64 / 1000.0);
         println!("h = {}", h);
 
-        let newb = StaticDiGraph::from_edge_file(Path::new(filename));
-        let _bfslevels = bfs(h, src);
+        let _bfslevels = bfs(&h, src);
         for _ in 0..NRUNS {
             let now = Instant::now();
-            let _levels = bfs(h, src);
+            let _levels = bfs(&h, src);
             let elp = now.elapsed().as_micros() as f64 / 1000.0;
             avg += elp;
             // println!("BFS took {}ms", elp);
@@ -47,6 +48,70 @@ fn main() {
             avg / NRUNS as f64
         );
     }
+    if op == "bfs_reuse" {
+        let now = Instant::now();
+        let h: StaticDiGraph<u32> = StaticDiGraph::from_edge_file(Path::new(filename));
+        println!("Load took {}ms", now.elapsed().as_micros() as f64 / 1000.0);
+        println!("h = {}", h);
+
+        let mut b = Bfs::new(h.nv() as usize);
+        b.run(&h, src);
+        for _ in 0..NRUNS {
+            let now = Instant::now();
+            b.run(&h, src);
+            let elp = now.elapsed().as_micros() as f64 / 1000.0;
+            avg += elp;
+            print!(".");
+        }
+        println!();
+        println!(
+            "bfs (reused state): average over {} runs: {:.3}ms",
+            NRUNS,
+            avg / NRUNS as f64
+        );
+    }
+    if op == "do_bfs" {
+        let now = Instant::now();
+        let h: StaticDiGraph<u32> = StaticDiGraph::from_edge_file(Path::new(filename));
+        println!("Load took {}ms", now.elapsed().as_micros() as f64 / 1000.0);
+        println!("h = {}", h);
+
+        let _levels = direction_optimizing_bfs(&h, src);
+        for _ in 0..NRUNS {
+            let now = Instant::now();
+            let _levels = direction_optimizing_bfs(&h, src);
+            let elp = now.elapsed().as_micros() as f64 / 1000.0;
+            avg += elp;
+            print!(".");
+        }
+        println!();
+        println!(
+            "direction-optimizing bfs: average over {} runs: {:.3}ms",
+            NRUNS,
+            avg / NRUNS as f64
+        );
+    }
+    if op == "par_bfs" {
+        let now = Instant::now();
+        let h: StaticDiGraph<u32> = StaticDiGraph::from_edge_file(Path::new(filename));
+        println!("Load took {}ms", now.elapsed().as_micros() as f64 / 1000.0);
+        println!("h = {}", h);
+
+        let _levels = par_bfs(&h, src);
+        for _ in 0..NRUNS {
+            let now = Instant::now();
+            let _levels = par_bfs(&h, src);
+            let elp = now.elapsed().as_micros() as f64 / 1000.0;
+            avg += elp;
+            print!(".");
+        }
+        println!();
+        println!(
+            "parallel bfs: average over {} runs: {:.3}ms",
+            NRUNS,
+            avg / NRUNS as f64
+        );
+    }
     if op == "dijkstra" {
         let now = Instant::now();
         let h: StaticDiGraph<u32> = StaticDiGraph::from_edge_file(Path::new(filename));
@@ -54,12 +119,12 @@ fn main() {
         println!("h = {}", h);
 
         println!("starting first dijkstra");
-        let _dists = dijkstra(h, src, weights);
+        let _dists = dijkstra(&h, src, weights);
         println!("starting looped dijkstra");
 
         for _ in 0..NRUNS {
             let now = Instant::now();
-            let _dists = dijkstra(h, src, weights);
+            let _dists = dijkstra(&h, src, weights);
             let elp = now.elapsed().as_micros() as f64 / 1000.0;
             avg += elp;
             print!(".");
@@ -141,5 +206,30 @@ fn main() {
             avg / NRUNS as f64
         );
     }
+    if op == "triangle_kernels" {
+        let now = Instant::now();
+        let h: StaticGraph<u32> = StaticGraph::from_edge_file(Path::new(filename));
+        println!("Load took {}ms", now.elapsed().as_micros() as f64 / 1000.0);
+        println!("h = {}", h);
+        let par = Parallelism::default();
+        for kernel in [Kernel::BinarySearch, Kernel::Merge, Kernel::Marker].iter() {
+            let ntri = threaded_triangles_with(&h, *kernel, &par);
+            println!("{:?}: {} triangles", kernel, ntri);
+            let mut avg: f64 = 0.0;
+            for _ in 0..NRUNS {
+                let now = Instant::now();
+                let _ntri = threaded_triangles_with(&h, *kernel, &par);
+                let elp = now.elapsed().as_micros() as f64 / 1000.0;
+                avg += elp;
+                print!(".");
+            }
+            println!();
+            println!(
+                "{:?} kernel: average over {} runs: {:.3}ms",
+                kernel,
+                NRUNS,
+                avg / NRUNS as f64
+            );
+        }
+    }
 }
-
@@ -0,0 +1,377 @@
+//! Matrix Market (`.mtx`) coordinate format import and export.
+//!
+//! `symmetric` matrices map to `StaticGraph` and `general` matrices to
+//! `StaticDiGraph`. Matrix Market indices are 1-based; vertices are 0-based.
+use std::io::{self, BufRead, Write};
+
+use crate::errors::LoadError;
+use crate::simplegraphs::SimpleVertex;
+use crate::{try_csr_from_edges, StaticDiGraph, StaticGraph, VOOR};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Pattern,
+    Real,
+    Integer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    General,
+    Symmetric,
+}
+
+/// The raw contents of a Matrix Market file. For `symmetric` files, only
+/// the entries actually present in the file are stored.
+pub struct MtxEntries<V> {
+    pub field: Field,
+    pub symmetry: Symmetry,
+    pub nv: usize,
+    pub edges: Vec<(V, V)>,
+    /// The value column, parallel to `edges`. `None` for `pattern` files.
+    pub values: Option<Vec<f64>>,
+}
+
+pub enum MtxGraph<V> {
+    Undirected(StaticGraph<V>),
+    Directed(StaticDiGraph<V>),
+}
+
+fn format_error(line: usize, text: &str) -> LoadError {
+    LoadError::Format { line, text: text.to_string() }
+}
+
+fn parse_header(line: usize, l: &str) -> Result<(Field, Symmetry), LoadError> {
+    let words: Vec<String> = l.split_whitespace().map(|w| w.to_lowercase()).collect();
+    if words.len() != 5 || words[0] != "%%matrixmarket" || words[1] != "matrix" {
+        return Err(format_error(line, "missing %%MatrixMarket matrix header"));
+    }
+    if words[2] != "coordinate" {
+        return Err(format_error(line, "only coordinate matrices are supported"));
+    }
+    let field = match words[3].as_str() {
+        "pattern" => Field::Pattern,
+        "real" => Field::Real,
+        "integer" => Field::Integer,
+        f => return Err(format_error(line, &format!("unsupported field {:?}", f))),
+    };
+    let symmetry = match words[4].as_str() {
+        "general" => Symmetry::General,
+        "symmetric" => Symmetry::Symmetric,
+        s => return Err(format_error(line, &format!("unsupported symmetry {:?}", s))),
+    };
+    Ok((field, symmetry))
+}
+
+fn parse_usize(field: &str, line: usize) -> Result<usize, LoadError> {
+    field.parse().map_err(|_| LoadError::Parse { line, text: field.to_string() })
+}
+
+/// Parses a 1-based index in `1..=nv` into a vertex.
+fn parse_index<V: SimpleVertex>(field: &str, line: usize, nv: usize) -> Result<V, LoadError> {
+    let i = parse_usize(field, line)?;
+    if i == 0 || i > nv {
+        return Err(LoadError::VertexOutOfRange { line, text: field.to_string() });
+    }
+    V::from(i - 1).ok_or_else(|| LoadError::VertexOutOfRange { line, text: field.to_string() })
+}
+
+/// The most entries `read_mtx_entries` will reserve space for up front.
+const MAX_RESERVE: usize = 1 << 20;
+
+/// Reads a Matrix Market coordinate file without building a graph.
+pub fn read_mtx_entries<V, R>(reader: R) -> Result<MtxEntries<V>, LoadError>
+where
+    V: SimpleVertex,
+    R: BufRead,
+{
+    let mut lines = reader.lines().enumerate().map(|(i, l)| {
+        l.map(|l| (i + 1, l)).map_err(|e| LoadError::Io { line: i + 1, source: e })
+    });
+
+    let (field, symmetry) = match lines.next() {
+        Some(l) => {
+            let (lineno, l) = l?;
+            parse_header(lineno, &l)?
+        }
+        None => return Err(format_error(0, "empty file")),
+    };
+    let nfields = if field == Field::Pattern { 2 } else { 3 };
+
+    let mut size: Option<(usize, usize)> = None;
+    let mut edges: Vec<(V, V)> = vec![];
+    let mut values: Vec<f64> = vec![];
+    for l in lines {
+        let (lineno, l) = l?;
+        let l = l.trim();
+        if l.is_empty() || l.starts_with("%") {
+            continue;
+        }
+        let fields: Vec<&str> = l.split_whitespace().collect();
+        let nv = match size {
+            Some((nv, _)) => nv,
+            None => {
+                if fields.len() != 3 {
+                    return Err(LoadError::FieldCount {
+                        line: lineno,
+                        text: l.to_string(),
+                        expected: 3,
+                        found: fields.len(),
+                    });
+                }
+                let rows = parse_usize(fields[0], lineno)?;
+                let cols = parse_usize(fields[1], lineno)?;
+                if rows != cols {
+                    return Err(format_error(lineno, "matrix is not square"));
+                }
+                // Every vertex index, and the vertex count itself (the end of
+                // `vertices()`), must fit in `V`, and the graph needs `rows + 1`
+                // row offsets.
+                if V::from(rows).is_none() || rows.checked_add(1).is_none() {
+                    return Err(LoadError::VertexOutOfRange { line: lineno, text: fields[0].to_string() });
+                }
+                let nnz = parse_usize(fields[2], lineno)?;
+                // The size line is untrusted, so don't let it pick the allocation.
+                edges.reserve(nnz.min(MAX_RESERVE));
+                size = Some((rows, nnz));
+                continue;
+            }
+        };
+        if fields.len() != nfields {
+            return Err(LoadError::FieldCount {
+                line: lineno,
+                text: l.to_string(),
+                expected: nfields,
+                found: fields.len(),
+            });
+        }
+        let src = parse_index(fields[0], lineno, nv)?;
+        let dst = parse_index(fields[1], lineno, nv)?;
+        edges.push((src, dst));
+        if nfields == 3 {
+            let x: f64 = fields[2]
+                .parse()
+                .map_err(|_| LoadError::Parse { line: lineno, text: fields[2].to_string() })?;
+            values.push(x);
+        }
+    }
+
+    let (nv, nnz) = size.ok_or_else(|| format_error(0, "missing size line"))?;
+    if edges.len() != nnz {
+        return Err(format_error(0, &format!("expected {} entries, found {}", nnz, edges.len())));
+    }
+    Ok(MtxEntries {
+        field,
+        symmetry,
+        nv,
+        edges,
+        values: if field == Field::Pattern { None } else { Some(values) },
+    })
+}
+
+impl<V> MtxEntries<V>
+where
+    V: SimpleVertex,
+{
+    /// Builds a graph with exactly `nv` vertices from the entries, ignoring
+    /// any values. Duplicate entries are dropped. Fails if `nv` vertices
+    /// cannot be allocated.
+    pub fn into_graph(self) -> Result<MtxGraph<V>, LoadError> {
+        let nv = self.nv;
+        let mut edges = self.edges;
+        Ok(match self.symmetry {
+            Symmetry::Symmetric => {
+                let redges: Vec<(V, V)> = edges.iter().map(|x| (x.1, x.0)).collect();
+                edges.extend(redges);
+                let (indptr, indices) = try_csr_from_edges(nv, edges)?;
+                MtxGraph::Undirected(StaticGraph::from_csr(indptr, indices).expect("invalid CSR"))
+            }
+            Symmetry::General => {
+                let (indptr, indices) = try_csr_from_edges(nv, edges)?;
+                MtxGraph::Directed(StaticDiGraph::from_csr(indptr, indices).expect("invalid CSR"))
+            }
+        })
+    }
+}
+
+/// Reads a Matrix Market coordinate file as a graph.
+pub fn read_mtx<V, R>(reader: R) -> Result<MtxGraph<V>, LoadError>
+where
+    V: SimpleVertex,
+    R: BufRead,
+{
+    read_mtx_entries(reader)?.into_graph()
+}
+
+impl<V> StaticGraph<V>
+where
+    V: SimpleVertex,
+{
+    /// Writes the graph as a `pattern symmetric` Matrix Market file,
+    /// storing the lower triangle as the format requires.
+    pub fn write_mtx<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let n = self.adj.dim();
+        let nnz: usize = (0..n)
+            .map(|u| {
+                let uu = V::from(u).expect(VOOR);
+                self.adj.row(uu).iter().filter(|v| **v <= uu).count()
+            })
+            .sum();
+        writeln!(w, "%%MatrixMarket matrix coordinate pattern symmetric")?;
+        writeln!(w, "{} {} {}", n, n, nnz)?;
+        for u in 0..n {
+            let uu = V::from(u).expect(VOOR);
+            for v in self.adj.row(uu).iter().filter(|v| **v <= uu) {
+                let vu: usize = v.as_();
+                writeln!(w, "{} {}", u + 1, vu + 1)?;
+            }
+        }
+        w.flush()
+    }
+}
+
+impl<V> StaticDiGraph<V>
+where
+    V: SimpleVertex,
+{
+    /// Writes the graph as a `pattern general` Matrix Market file.
+    pub fn write_mtx<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let n = self.fadj.dim();
+        writeln!(w, "%%MatrixMarket matrix coordinate pattern general")?;
+        writeln!(w, "{} {} {}", n, n, self.fadj.n())?;
+        for u in 0..n {
+            for v in self.fadj.row(V::from(u).expect(VOOR)) {
+                let vu: usize = v.as_();
+                writeln!(w, "{} {}", u + 1, vu + 1)?;
+            }
+        }
+        w.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{digraph, graph, random_edges};
+    use crate::traits::Graph;
+    use crate::weighted::MtxWeightedGraph;
+
+    fn entries(text: &str) -> Result<MtxEntries<u32>, LoadError> {
+        read_mtx_entries(text.as_bytes())
+    }
+
+    fn undirected(text: &str) -> StaticGraph<u32> {
+        match read_mtx(text.as_bytes()).unwrap() {
+            MtxGraph::Undirected(g) => g,
+            MtxGraph::Directed(_) => panic!("expected an undirected graph"),
+        }
+    }
+
+    fn directed(text: &str) -> StaticDiGraph<u32> {
+        match read_mtx(text.as_bytes()).unwrap() {
+            MtxGraph::Directed(g) => g,
+            MtxGraph::Undirected(_) => panic!("expected a directed graph"),
+        }
+    }
+
+    #[test]
+    fn reads_symmetric_and_general() {
+        let text = "%%MatrixMarket matrix coordinate pattern symmetric\n% a comment\n\n5 5 3\n2 1\n3 2\n4 1\n";
+        let g = undirected(text);
+        assert_eq!((g.nv(), g.ne()), (5, 3));
+        assert_eq!(g.out_neighbors(0), &[1, 3]);
+        assert_eq!(g.out_neighbors(1), &[0, 2]);
+        assert!(g.out_neighbors(4).is_empty());
+
+        let g = directed("%%MatrixMarket matrix coordinate pattern general\n3 3 2\n1 2\n3 1\n");
+        assert_eq!((g.nv(), g.ne()), (3, 2));
+        assert_eq!(g.out_neighbors(0), &[1]);
+        assert_eq!(g.out_neighbors(2), &[0]);
+        assert_eq!(g.in_neighbors(0), &[2]);
+    }
+
+    #[test]
+    fn keeps_the_value_column() {
+        let text = "%%MatrixMarket matrix coordinate real general\n3 3 2\n1 2 0.5\n3 1 -2e3\n";
+        let m = entries(text).unwrap();
+        assert_eq!((m.field, m.symmetry, m.nv), (Field::Real, Symmetry::General, 3));
+        assert_eq!(m.edges, vec![(0, 1), (2, 0)]);
+        assert_eq!(m.values, Some(vec![0.5, -2000.0]));
+        match m.into_weighted_graph().unwrap() {
+            MtxWeightedGraph::Directed(g) => {
+                assert_eq!(g.out_edges_weighted(0).collect::<Vec<_>>(), vec![(1, 0.5)]);
+                assert_eq!(g.out_edges_weighted(2).collect::<Vec<_>>(), vec![(0, -2000.0)]);
+            }
+            MtxWeightedGraph::Undirected(_) => panic!("expected a directed graph"),
+        }
+
+        let text = "%%MatrixMarket matrix coordinate integer symmetric\n3 3 1\n3 1 7\n";
+        match entries(text).unwrap().into_weighted_graph().unwrap() {
+            MtxWeightedGraph::Undirected(g) => {
+                assert_eq!(g.out_edges_weighted(0).collect::<Vec<_>>(), vec![(2, 7.0)]);
+                assert_eq!(g.out_edges_weighted(2).collect::<Vec<_>>(), vec![(0, 7.0)]);
+            }
+            MtxWeightedGraph::Directed(_) => panic!("expected an undirected graph"),
+        }
+
+        let m = entries("%%MatrixMarket matrix coordinate pattern general\n2 2 1\n1 2\n").unwrap();
+        assert_eq!(m.values, None);
+        assert!(m.into_weighted_graph().is_err());
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let bad = |text: &str| entries(text).err().expect("expected an error");
+        assert_eq!(bad("").line(), 0);
+        assert!(matches!(bad("%%MatrixMarket matrix array real general\n"), LoadError::Format { line: 1, .. }));
+        assert!(matches!(bad("%%MatrixMarket matrix coordinate complex general\n"), LoadError::Format { line: 1, .. }));
+        let header = "%%MatrixMarket matrix coordinate pattern general\n";
+        assert!(matches!(bad(&format!("{}3 4 0\n", header)), LoadError::Format { line: 2, .. }));
+        assert!(matches!(bad(&format!("{}3 3\n", header)), LoadError::FieldCount { line: 2, expected: 3, found: 2, .. }));
+        // Indices are 1-based and must be in `1..=nv`.
+        assert!(matches!(bad(&format!("{}3 3 1\n0 1\n", header)), LoadError::VertexOutOfRange { line: 3, .. }));
+        assert!(matches!(bad(&format!("{}3 3 1\n1 4\n", header)), LoadError::VertexOutOfRange { line: 3, .. }));
+        assert!(matches!(bad(&format!("{}3 3 1\n1 2 5\n", header)), LoadError::FieldCount { line: 3, expected: 2, .. }));
+        // The entry count on the size line must match the entries.
+        assert_eq!(bad(&format!("{}3 3 2\n1 2\n", header)).to_string(), "line 0: invalid format: expected 2 entries, found 1");
+        assert!(bad(&format!("{}3 3 1\n1 2\n2 3\n", header)).to_string().contains("expected 1 entries, found 2"));
+        assert!(matches!(bad("%%MatrixMarket matrix coordinate real general\n2 2 1\n1 2 x\n"), LoadError::Parse { line: 3, .. }));
+    }
+
+    #[test]
+    fn rejects_sizes_that_cannot_be_allocated() {
+        let header = "%%MatrixMarket matrix coordinate pattern general\n";
+        // Too large for `V`.
+        let text = format!("{}256 256 0\n", header);
+        assert!(matches!(read_mtx_entries::<u8, _>(text.as_bytes()), Err(LoadError::VertexOutOfRange { line: 2, .. })));
+        // `rows + 1` overflows.
+        let text = format!("{}{} {} 0\n", header, usize::MAX, usize::MAX);
+        assert!(matches!(read_mtx_entries::<usize, _>(text.as_bytes()), Err(LoadError::VertexOutOfRange { line: 2, .. })));
+        // Fits `V` but the row offsets can never be allocated.
+        let text = format!("{}{} {} 0\n", header, 1u64 << 61, 1u64 << 61);
+        assert!(matches!(read_mtx::<u64, _>(text.as_bytes()), Err(LoadError::Format { line: 0, .. })));
+    }
+
+    #[test]
+    fn write_round_trips() {
+        let mut edges = random_edges(30, 80, 5);
+        edges.push((4, 4));
+        let g = graph(35, edges.clone());
+        let mut buf = vec![];
+        g.write_mtx(&mut buf).unwrap();
+        let h = undirected(std::str::from_utf8(&buf).unwrap());
+        assert_eq!((h.nv(), h.ne()), (g.nv(), g.ne()));
+        for v in g.vertices() {
+            assert_eq!(h.out_neighbors(v), g.out_neighbors(v));
+        }
+
+        let g = digraph(35, edges);
+        let mut buf = vec![];
+        g.write_mtx(&mut buf).unwrap();
+        let h = directed(std::str::from_utf8(&buf).unwrap());
+        assert_eq!((h.nv(), h.ne()), (g.nv(), g.ne()));
+        for v in g.vertices() {
+            assert_eq!(h.out_neighbors(v), g.out_neighbors(v));
+        }
+    }
+}
@@ -1,5 +1,5 @@
 pub trait SimpleVertex:
-    crate::traits::Vertex +
+    crate::traits::Vertex<T = Self> +
     graph_matrix::MxElement +
     std::fmt::Display +
     std::hash::Hash +
@@ -0,0 +1,26 @@
+//! Graph fixtures shared by the unit tests.
+use crate::{csr_from_edges, StaticDiGraph, StaticGraph};
+
+/// `m` pseudo-random edges on `n` vertices, reproducible from `seed`.
+/// May contain self-loops and repeated edges.
+pub(crate) fn random_edges(n: u64, m: usize, mut seed: u64) -> Vec<(u32, u32)> {
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((seed >> 33) % n) as u32
+    };
+    (0..m).map(|_| (next(), next())).collect()
+}
+
+/// The undirected graph on `n` vertices with `edges` in both directions.
+pub(crate) fn graph(n: usize, edges: Vec<(u32, u32)>) -> StaticGraph<u32> {
+    let mut all = edges.clone();
+    all.extend(edges.into_iter().map(|(u, v)| (v, u)));
+    let (indptr, indices) = csr_from_edges(n, all);
+    StaticGraph::from_csr(indptr, indices).unwrap()
+}
+
+/// The directed graph on `n` vertices with `edges`.
+pub(crate) fn digraph(n: usize, edges: Vec<(u32, u32)>) -> StaticDiGraph<u32> {
+    let (indptr, indices) = csr_from_edges(n, edges);
+    StaticDiGraph::from_csr(indptr, indices).unwrap()
+}
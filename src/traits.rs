@@ -1,16 +1,19 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::path::Path;
 use num;
+use crate::errors::LoadError;
 
 
-pub trait Vertex: Clone {
-    type T: num::Bounded + num::cast::NumCast + std::cmp::Ord + num::Zero + num::One;  // this is a type that will be used for methods that return an integer type (out_degree, etc.)
+pub trait Vertex: Copy {
+    type T: Copy + num::Bounded + num::cast::NumCast + num::cast::AsPrimitive<usize> + std::cmp::Ord + num::Zero + num::One;  // this is a type that will be used for methods that return an integer type (out_degree, etc.)
     fn index(&self) -> Self::T;
     fn from_index(x:Self::T) -> Self;
     fn sentinel() -> Self;
 }
 
 // Anything that can cast to primitive can use to_usize.
-impl<T: Clone + num::Bounded + num::cast::NumCast + std::cmp::Ord + num::Zero + num::One> Vertex for T {
+impl<T: Copy + num::Bounded + num::cast::NumCast + num::cast::AsPrimitive<usize> + std::cmp::Ord + num::Zero + num::One> Vertex for T {
     type T = T;
     fn index(&self) -> T { *self }
     fn from_index(x:T) -> Self { x as Self }
@@ -43,6 +46,91 @@ where
     fn in_neighbors(&self, v: V) -> &[V];
     fn out_neighbors(&self, v: V) -> &[V];
     fn has_edge(&self, e: E) -> bool;
-    fn from_edge_file(fname: &Path) -> Self;
 }
 
+/// Graphs that can be built from a whitespace-separated edge list. This is
+/// kept apart from `Graph` so that algorithms never inherit the bounds that
+/// parsing needs, such as `W: FromStr` for weighted graphs.
+pub trait FromEdgeList: Sized {
+    fn try_from_edge_reader<R: BufRead>(reader: R) -> Result<Self, LoadError>;
+
+    /// Like `try_from_edge_reader`, but panics on any error.
+    fn from_edge_reader<R: BufRead>(reader: R) -> Self {
+        Self::try_from_edge_reader(reader).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_from_edge_file(fname: &Path) -> Result<Self, LoadError> {
+        let f = File::open(fname).map_err(|e| LoadError::Io { line: 0, source: e })?;
+        Self::try_from_edge_reader(BufReader::new(f))
+    }
+
+    /// Like `try_from_edge_file`, but panics on any error.
+    fn from_edge_file(fname: &Path) -> Self {
+        Self::try_from_edge_file(fname).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+/// A graph whose edges have no direction: every edge `u -> v` is also an
+/// edge `v -> u`, so in- and out-neighbors coincide. Algorithms that are
+/// only meaningful for undirected graphs (triangle counting, clustering)
+/// take this bound rather than a concrete graph type.
+pub trait UndirectedGraph<V, E>: Graph<V, E>
+where
+    V: Vertex,
+    E: Edge<V>,
+{
+    fn neighbors(&self, v: V) -> &[V] {
+        self.out_neighbors(v)
+    }
+
+    fn degree(&self, v: V) -> V::T {
+        self.out_degree(v)
+    }
+}
+
+/// A graph whose edges have a direction, so that `in_neighbors` and
+/// `out_neighbors` generally differ.
+pub trait DirectedGraph<V, E>: Graph<V, E>
+where
+    V: Vertex,
+    E: Edge<V>,
+{
+    fn predecessors(&self, v: V) -> &[V] {
+        self.in_neighbors(v)
+    }
+
+    fn successors(&self, v: V) -> &[V] {
+        self.out_neighbors(v)
+    }
+
+    /// The sorted union of in- and out-neighbors of `v`: its neighbors in
+    /// the underlying undirected graph.
+    fn all_neighbors(&self, v: V) -> Vec<V> where V: std::cmp::Ord {
+        let ins = self.in_neighbors(v);
+        let outs = self.out_neighbors(v);
+        let mut all: Vec<V> = Vec::with_capacity(ins.len() + outs.len());
+        let (mut i, mut j) = (0, 0);
+        while i < ins.len() && j < outs.len() {
+            if ins[i] < outs[j] {
+                all.push(ins[i]);
+                i += 1;
+            } else {
+                all.push(outs[j]);
+                j += 1;
+            }
+        }
+        all.extend_from_slice(&ins[i..]);
+        all.extend_from_slice(&outs[j..]);
+        all.dedup();
+        all
+    }
+}
+
+pub trait WeightedGraph<V, E, W>: Graph<V, E>
+where
+    V: Vertex,
+    E: Edge<V>,
+{
+    /// The weights of the out-edges of `v`, parallel to `out_neighbors(v)`.
+    fn out_weights(&self, v: V) -> &[W];
+}
@@ -1,104 +1,929 @@
+use std::cmp::Reverse;
 use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
 use bitvec::prelude as bv;
 use ordered_float::OrderedFloat;
 use priority_queue::PriorityQueue;
-use crate::traits::{Graph, Edge, Vertex};
+use rayon::prelude::*;
+use crate::traits::{Graph, Edge, Vertex, WeightedGraph};
 use num::cast::AsPrimitive;
 use num::{Bounded, Zero, One};
 
 pub fn bfs<V, E>(g: &impl Graph<V, E>, src: V) -> Vec<V::T> where V: Vertex, E: Edge<V> {
-    let n = g.nv();
-    let maxdeg = g
-        .vertices()
-        .map(|v| g.out_degree(v))
-        .max()
-        .expect("Invalid degree found")
-        .as_();
-    let mut visited: bv::BitVec<bv::Lsb0, u64> = bv::BitVec::repeat(false, n.as_());
-
-    let mut levels: Vec<V::T> = vec![V::T::max_value(); n.as_()];
-    let mut cur_level: Vec<V> = Vec::new();
-    cur_level.reserve(maxdeg);
+    let mut b = Bfs::new(g.nv().as_());
+    b.run(g, src);
+    b.into_levels()
+}
+
+/// Reusable breadth-first search state.
+///
+/// All buffers are allocated by `new` and only cleared between calls to
+/// `run`, so repeated searches over the same graph do not reallocate.
+/// Parents and visitation order are only recorded if requested with
+/// `with_parents` and `with_order`.
+pub struct Bfs<V: Vertex> {
+    visited: bv::BitVec<bv::Lsb0, u64>,
+    levels: Vec<V::T>,
+    parents: Option<Vec<V>>,
+    order: Option<Vec<V>>,
+    cur_level: Vec<V>,
+    next_level: Vec<V>,
+}
+
+impl<V> Bfs<V>
+where
+    V: Vertex,
+{
+    /// Creates search state for graphs with `n` vertices.
+    pub fn new(n: usize) -> Self {
+        Bfs {
+            visited: bv::BitVec::repeat(false, n),
+            levels: vec![V::T::max_value(); n],
+            parents: None,
+            order: None,
+            cur_level: Vec::new(),
+            next_level: Vec::new(),
+        }
+    }
+
+    /// Records the BFS tree; see `parents` and `path_to`.
+    pub fn with_parents(mut self) -> Self {
+        let n = self.levels.len();
+        self.parents = Some(vec![V::sentinel(); n]);
+        self
+    }
+
+    /// Records the order in which vertices are visited; see `order`.
+    pub fn with_order(mut self) -> Self {
+        self.order = Some(Vec::with_capacity(self.levels.len()));
+        self
+    }
+
+    /// Runs a full search from `src`.
+    pub fn run<E>(&mut self, g: &impl Graph<V, E>, src: V) where E: Edge<V> {
+        self.search(g, src, None);
+    }
+
+    /// Runs a search from `src` that stops as soon as `target` is reached.
+    /// Returns whether `target` was reached; vertices beyond it are left
+    /// unvisited.
+    pub fn run_to<E>(&mut self, g: &impl Graph<V, E>, src: V, target: V) -> bool where E: Edge<V> {
+        self.search(g, src, Some(target.index().as_()))
+    }
+
+    fn reset(&mut self, n: usize) {
+        if self.levels.len() != n {
+            self.visited.resize(n, false);
+            self.levels.resize(n, V::T::max_value());
+            if let Some(p) = self.parents.as_mut() {
+                p.resize(n, V::sentinel());
+            }
+        }
+        self.visited.set_all(false);
+        self.levels.iter_mut().for_each(|l| *l = V::T::max_value());
+        if let Some(p) = self.parents.as_mut() {
+            p.iter_mut().for_each(|p| *p = V::sentinel());
+        }
+        if let Some(o) = self.order.as_mut() {
+            o.clear();
+        }
+        self.cur_level.clear();
+        self.next_level.clear();
+    }
+
+    fn search<E>(&mut self, g: &impl Graph<V, E>, src: V, target: Option<usize>) -> bool where E: Edge<V> {
+        self.reset(g.nv().as_());
+
+        let s = src.index().as_();
+        self.visited.set(s, true);
+        self.levels[s] = V::T::zero();
+        if let Some(o) = self.order.as_mut() {
+            o.push(src);
+        }
+        if target == Some(s) {
+            return true;
+        }
+        self.cur_level.push(src);
+
+        let mut n_level = V::T::one();
+
+        while !self.cur_level.is_empty() {
+            for v in self.cur_level.iter() {
+                for i in g.out_neighbors(*v) {
+                    let ui = i.index().as_();
+                    if unsafe { !*self.visited.get_unchecked(ui) } {
+                        self.next_level.push(*i);
+                        unsafe {
+                            self.visited.set_unchecked(ui, true);
+                            *self.levels.get_unchecked_mut(ui) = n_level;
+                        }
+                        if let Some(p) = self.parents.as_mut() {
+                            p[ui] = *v;
+                        }
+                        if let Some(o) = self.order.as_mut() {
+                            o.push(*i);
+                        }
+                        if target == Some(ui) {
+                            return true;
+                        }
+                    }
+                }
+            }
+            n_level = n_level + V::T::one();
+            self.cur_level.clear();
+
+            mem::swap(&mut self.cur_level, &mut self.next_level);
+            self.cur_level.sort_unstable_by_key(|x| x.index());
+        }
+        false
+    }
+
+    /// The level of every vertex from the last search, with
+    /// `V::T::max_value()` for unvisited vertices.
+    pub fn levels(&self) -> &[V::T] {
+        &self.levels
+    }
 
+    pub fn into_levels(self) -> Vec<V::T> {
+        self.levels
+    }
+
+    /// The BFS parent of every vertex, with `V::sentinel()` for the source
+    /// and unvisited vertices. `None` unless built `with_parents`.
+    pub fn parents(&self) -> Option<&[V]> {
+        self.parents.as_deref()
+    }
+
+    /// The vertices visited by the last search, in visitation order.
+    /// `None` unless built `with_order`.
+    pub fn order(&self) -> Option<&[V]> {
+        self.order.as_deref()
+    }
+
+    /// A shortest path from the last source to `target`, including both
+    /// ends. `None` if `target` was not visited or parents are not recorded.
+    pub fn path_to(&self, target: V) -> Option<Vec<V>> where V: std::cmp::Eq {
+        let parents = self.parents.as_ref()?;
+        let t = target.index().as_();
+        if self.levels[t] == V::T::max_value() {
+            return None;
+        }
+        let mut path = vec![target];
+        let mut cur = t;
+        while parents[cur] != V::sentinel() {
+            let p = parents[cur];
+            cur = p.index().as_();
+            path.push(p);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Direction-optimizing BFS (Beamer, Asanović & Patterson, 2012).
+///
+/// Expands the frontier top-down along `out_neighbors` while it is small,
+/// and switches to bottom-up steps, in which each unvisited vertex scans its
+/// `in_neighbors` for a frontier member, once the frontier's out-edges
+/// outnumber a fraction of the unexplored edges. Returns the same levels as
+/// `bfs`.
+pub fn direction_optimizing_bfs<V, E>(g: &impl Graph<V, E>, src: V) -> Vec<V::T> where V: Vertex, E: Edge<V> {
+    // switch to bottom-up when frontier edges > unexplored edges / ALPHA;
+    // switch back when the frontier shrinks below nv / BETA vertices.
+    const ALPHA: usize = 15;
+    const BETA: usize = 18;
+
+    let n: usize = g.nv().as_();
+    let deg = |v: V| -> usize { g.out_degree(v).as_() };
+
+    let mut visited: bv::BitVec<bv::Lsb0, u64> = bv::BitVec::repeat(false, n);
+    let mut front_bits: bv::BitVec<bv::Lsb0, u64> = bv::BitVec::repeat(false, n);
+    let mut levels: Vec<V::T> = vec![V::T::max_value(); n];
+    let mut cur_level: Vec<V> = Vec::new();
     let mut next_level: Vec<V> = Vec::new();
-    next_level.reserve(maxdeg);
 
     let s = src.index().as_();
     visited.set(s, true);
+    levels[s] = V::T::zero();
     cur_level.push(src);
+
+    // edges out of the frontier, and edges out of unvisited vertices.
+    let mut scout: usize = deg(src);
+    let mut edges_to_check: usize = g.vertices().map(deg).sum::<usize>() - scout;
+
+    let mut n_level = V::T::one();
+
+    while !cur_level.is_empty() {
+        if scout > edges_to_check / ALPHA {
+            let mut awake = cur_level.len();
+            loop {
+                let old_awake = awake;
+                front_bits.set_all(false);
+                for v in cur_level.iter() {
+                    front_bits.set(v.index().as_(), true);
+                }
+                for v in g.vertices() {
+                    let vi = v.index().as_();
+                    if visited[vi] {
+                        continue;
+                    }
+                    if g.in_neighbors(v).iter().any(|u| front_bits[u.index().as_()]) {
+                        visited.set(vi, true);
+                        levels[vi] = n_level;
+                        edges_to_check -= deg(v);
+                        next_level.push(v);
+                    }
+                }
+                n_level = n_level + V::T::one();
+                cur_level.clear();
+                mem::swap(&mut cur_level, &mut next_level);
+                awake = cur_level.len();
+                if awake == 0 || (awake < old_awake && awake <= n / BETA) {
+                    break;
+                }
+            }
+            scout = cur_level.iter().map(|v| deg(*v)).sum();
+        } else {
+            let mut next_scout = 0usize;
+            for v in cur_level.iter() {
+                for i in g.out_neighbors(*v) {
+                    let ui = i.index().as_();
+                    if unsafe { !*visited.get_unchecked(ui) } {
+                        next_level.push(*i);
+                        unsafe {
+                            visited.set_unchecked(ui, true);
+                            *levels.get_unchecked_mut(ui) = n_level;
+                        }
+                        let d = deg(*i);
+                        next_scout += d;
+                        edges_to_check -= d;
+                    }
+                }
+            }
+            n_level = n_level + V::T::one();
+            cur_level.clear();
+            mem::swap(&mut cur_level, &mut next_level);
+            cur_level.sort_unstable_by_key(|x| x.index());
+            scout = next_scout;
+        }
+    }
+    levels
+}
+
+/// Level-synchronous BFS that expands each frontier in parallel.
+///
+/// Vertices are claimed with an atomic `fetch_or` on a shared visited
+/// bitmap, so each is added to the next frontier exactly once. Returns the
+/// same levels as `bfs`.
+pub fn par_bfs<V, E, G>(g: &G, src: V) -> Vec<V::T>
+where
+    V: Vertex + Send + Sync,
+    E: Edge<V>,
+    G: Graph<V, E> + Sync,
+{
+    let n: usize = g.nv().as_();
+    let visited: Vec<AtomicU64> = (0..n.div_ceil(64)).map(|_| AtomicU64::new(0)).collect();
+    // returns true if this call is the one that marked `ui` visited.
+    let claim = |ui: usize| -> bool {
+        let word = &visited[ui / 64];
+        let mask = 1u64 << (ui % 64);
+        word.load(Ordering::Relaxed) & mask == 0 && word.fetch_or(mask, Ordering::Relaxed) & mask == 0
+    };
+
+    let mut levels: Vec<V::T> = vec![V::T::max_value(); n];
+    let s = src.index().as_();
+    claim(s);
     levels[s] = V::T::zero();
 
+    let mut cur_level: Vec<V> = vec![src];
     let mut n_level = V::T::one();
 
-    // println!("cur_level = {:?}", cur_level);
+    while !cur_level.is_empty() {
+        let mut next_level: Vec<V> = cur_level
+            .par_iter()
+            .fold(Vec::new, |mut acc, v| {
+                acc.extend(g.out_neighbors(*v).iter().filter(|i| claim(i.index().as_())).cloned());
+                acc
+            })
+            .reduce(Vec::new, |mut a, mut b| {
+                a.append(&mut b);
+                a
+            });
+        for v in next_level.iter() {
+            levels[v.index().as_()] = n_level;
+        }
+        n_level = n_level + V::T::one();
+        next_level.par_sort_unstable_by_key(|x| x.index());
+        cur_level = next_level;
+    }
+    levels
+}
+
+/// BFS seeded from every vertex in `sources` at once.
+///
+/// Returns, for every vertex, the hop distance to its nearest source and
+/// which source that is (`V::sentinel()` if no source reaches it). Ties are
+/// broken in favor of the source listed first.
+pub fn multi_source_bfs<V, E>(g: &impl Graph<V, E>, sources: &[V]) -> (Vec<V::T>, Vec<V>) where V: Vertex, E: Edge<V> {
+    let n: usize = g.nv().as_();
+    let mut visited: bv::BitVec<bv::Lsb0, u64> = bv::BitVec::repeat(false, n);
+    let mut levels: Vec<V::T> = vec![V::T::max_value(); n];
+    let mut nearest: Vec<V> = vec![V::sentinel(); n];
+    let mut cur_level: Vec<V> = Vec::new();
+    let mut next_level: Vec<V> = Vec::new();
+
+    for src in sources {
+        let s = src.index().as_();
+        if !visited[s] {
+            visited.set(s, true);
+            levels[s] = V::T::zero();
+            nearest[s] = *src;
+            cur_level.push(*src);
+        }
+    }
+
+    let mut n_level = V::T::one();
     while !cur_level.is_empty() {
         for v in cur_level.iter() {
+            let from = nearest[v.index().as_()];
             for i in g.out_neighbors(*v) {
-                // println!("neighbor {:?}", i);
                 let ui = i.index().as_();
                 if unsafe { !*visited.get_unchecked(ui) } {
-                    // println!("{:?} -> {}", v, ui);
                     next_level.push(*i);
                     unsafe {
                         visited.set_unchecked(ui, true);
                         *levels.get_unchecked_mut(ui) = n_level;
+                        *nearest.get_unchecked_mut(ui) = from;
                     }
                 }
             }
         }
         n_level = n_level + V::T::one();
-        // println!("next_level = {:?}", next_level);
         cur_level.clear();
-
+        // frontier order is kept (not sorted) so ties go to earlier sources.
         mem::swap(&mut cur_level, &mut next_level);
-        cur_level.sort_unstable_by_key(|x| x.index());
     }
-    levels
+    (levels, nearest)
+}
+
+/// Bit-parallel multi-source BFS (Then et al., "The More the Merrier", 2014).
+///
+/// Runs up to 64 independent searches, one per entry of `sources`, sharing
+/// a single pass over the graph per level: each vertex carries a `u64` word
+/// whose bit `i` tracks the search from `sources[i]`. Calls
+/// `visit(i, v, level)` the first time search `i` reaches `v`.
+pub fn msbfs<V, E, F>(g: &impl Graph<V, E>, sources: &[V], mut visit: F)
+where
+    V: Vertex,
+    E: Edge<V>,
+    F: FnMut(usize, V, V::T),
+{
+    assert!(sources.len() <= 64, "msbfs supports at most 64 sources per batch");
+    let n: usize = g.nv().as_();
+    let mut seen = vec![0u64; n];
+    let mut frontier = vec![0u64; n];
+    let mut next = vec![0u64; n];
+
+    for (i, src) in sources.iter().enumerate() {
+        let s = src.index().as_();
+        seen[s] |= 1 << i;
+        frontier[s] |= 1 << i;
+        visit(i, *src, V::T::zero());
+    }
+
+    let mut n_level = V::T::one();
+    let mut active = !sources.is_empty();
+    while active {
+        for v in g.vertices() {
+            let bits = frontier[v.index().as_()];
+            if bits == 0 {
+                continue;
+            }
+            for i in g.out_neighbors(v) {
+                next[i.index().as_()] |= bits;
+            }
+        }
+        active = false;
+        for v in g.vertices() {
+            let vi = v.index().as_();
+            let mut new = next[vi] & !seen[vi];
+            next[vi] = new;
+            if new == 0 {
+                continue;
+            }
+            active = true;
+            seen[vi] |= new;
+            while new != 0 {
+                let i = new.trailing_zeros() as usize;
+                visit(i, v, n_level);
+                new &= new - 1;
+            }
+        }
+        n_level = n_level + V::T::one();
+        mem::swap(&mut frontier, &mut next);
+        next.iter_mut().for_each(|x| *x = 0);
+    }
 }
-pub fn dijkstra<V, E, W>(g: &impl Graph<V, E>, v: V, weights: fn(V, V) -> W) -> Vec<W>
+
+/// Hop distances between all pairs of vertices: `dists[s][v]` is the BFS
+/// level of `v` from `s`, or `V::T::max_value()` if unreachable.
+///
+/// Sources are processed 64 at a time with `msbfs`, and batches run in
+/// parallel. The result takes `nv()^2` entries of memory.
+pub fn all_pairs_hop_distances<V, E, G>(g: &G) -> Vec<Vec<V::T>>
+where
+    V: Vertex + Send + Sync,
+    V::T: Send,
+    E: Edge<V>,
+    G: Graph<V, E> + Sync,
+{
+    let n: usize = g.nv().as_();
+    let all: Vec<V> = g.vertices().collect();
+    let batches: Vec<Vec<Vec<V::T>>> = all
+        .par_chunks(64)
+        .map(|batch| {
+            let mut rows = vec![vec![V::T::max_value(); n]; batch.len()];
+            msbfs(g, batch, |i, v, level| rows[i][v.index().as_()] = level);
+            rows
+        })
+        .collect();
+    batches.into_iter().flatten().collect()
+}
+
+/// Callbacks invoked during `dfs`. Every method defaults to doing nothing,
+/// so visitors only implement the events they need.
+///
+/// Edges are classified as in a directed graph; for undirected graphs each
+/// edge is seen from both ends, so the edge back to a vertex's tree parent
+/// is reported as a `back_edge`.
+pub trait DfsVisitor<V> {
+    fn discover_vertex(&mut self, _v: V, _time: usize) {}
+    fn tree_edge(&mut self, _u: V, _v: V) {}
+    fn back_edge(&mut self, _u: V, _v: V) {}
+    fn forward_or_cross_edge(&mut self, _u: V, _v: V) {}
+    fn finish_vertex(&mut self, _v: V, _time: usize) {}
+}
+
+impl<V> DfsVisitor<V> for () {}
+
+/// Discover and finish times from `dfs`, on a single shared clock.
+/// Vertices that were never reached have both times set to `usize::MAX`.
+pub struct DfsTimes {
+    pub discover: Vec<usize>,
+    pub finish: Vec<usize>,
+}
+
+/// Depth-first search over every vertex of `g`, starting new trees from
+/// unvisited vertices in vertex order.
+pub fn dfs<V, E, Vis>(g: &impl Graph<V, E>, visitor: &mut Vis) -> DfsTimes
+where
+    V: Vertex,
+    E: Edge<V>,
+    Vis: DfsVisitor<V>,
+{
+    dfs_impl(g, g.vertices(), visitor)
+}
+
+/// Depth-first search over the vertices reachable from `src`.
+pub fn dfs_from<V, E, Vis>(g: &impl Graph<V, E>, src: V, visitor: &mut Vis) -> DfsTimes
+where
+    V: Vertex,
+    E: Edge<V>,
+    Vis: DfsVisitor<V>,
+{
+    dfs_impl(g, std::iter::once(src), visitor)
+}
+
+/// Iterative DFS: the stack holds `(vertex, index of next out-neighbor)`, so
+/// search depth is bounded by memory rather than the call stack.
+fn dfs_impl<V, E, G, I, Vis>(g: &G, roots: I, visitor: &mut Vis) -> DfsTimes
+where
+    V: Vertex,
+    E: Edge<V>,
+    G: Graph<V, E>,
+    I: Iterator<Item = V>,
+    Vis: DfsVisitor<V>,
+{
+    let n: usize = g.nv().as_();
+    let mut discover = vec![usize::MAX; n];
+    let mut finish = vec![usize::MAX; n];
+    let mut stack: Vec<(V, usize)> = Vec::new();
+    let mut time = 0usize;
+
+    for root in roots {
+        let r = root.index().as_();
+        if discover[r] != usize::MAX {
+            continue;
+        }
+        discover[r] = time;
+        visitor.discover_vertex(root, time);
+        time += 1;
+        stack.push((root, 0));
+
+        while let Some(top) = stack.last_mut() {
+            let u = top.0;
+            let nbrs = g.out_neighbors(u);
+            if top.1 < nbrs.len() {
+                let v = nbrs[top.1];
+                top.1 += 1;
+                let vi = v.index().as_();
+                if discover[vi] == usize::MAX {
+                    visitor.tree_edge(u, v);
+                    discover[vi] = time;
+                    visitor.discover_vertex(v, time);
+                    time += 1;
+                    stack.push((v, 0));
+                } else if finish[vi] == usize::MAX {
+                    visitor.back_edge(u, v);
+                } else {
+                    visitor.forward_or_cross_edge(u, v);
+                }
+            } else {
+                finish[u.index().as_()] = time;
+                visitor.finish_vertex(u, time);
+                time += 1;
+                stack.pop();
+            }
+        }
+    }
+    DfsTimes { discover, finish }
+}
+
+/// Distances and the shortest-path tree from a single source, as returned
+/// by `dijkstra`. Unreachable vertices have an infinite distance.
+pub struct ShortestPaths<V, W> {
+    pub src: V,
+    pub dists: Vec<W>,
+    pub parents: Vec<V>,
+}
+
+impl<V, W> ShortestPaths<V, W>
+where
+    V: Vertex + std::cmp::Eq,
+    W: Copy,
+{
+    pub fn dist(&self, v: V) -> W {
+        self.dists[v.index().as_()]
+    }
+
+    /// The vertex preceding `v` on a shortest path from the source, or
+    /// `None` if `v` is the source or is unreachable.
+    pub fn predecessor(&self, v: V) -> Option<V> {
+        let p = self.parents[v.index().as_()];
+        if p == V::sentinel() {
+            None
+        } else {
+            Some(p)
+        }
+    }
+
+    /// The predecessor of every vertex, with `V::sentinel()` for the source
+    /// and for unreachable vertices.
+    pub fn predecessors(&self) -> &[V] {
+        &self.parents
+    }
+
+    /// A shortest path from the source to `target`, including both ends,
+    /// or `None` if `target` is unreachable.
+    pub fn path_to(&self, target: V) -> Option<Vec<V>> {
+        let mut path = vec![target];
+        let mut cur = target;
+        while cur != self.src {
+            cur = self.predecessor(cur)?;
+            path.push(cur);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+pub fn dijkstra<V, E, W>(g: &impl Graph<V, E>, v: V, weights: fn(V, V) -> W) -> ShortestPaths<V, W>
+where
+    V: Vertex + std::hash::Hash + std::cmp::Eq,
+    E: Edge<V>,
+    W: num::Float,
+{
+    dijkstra_impl(g, v, |u, i| weights(u, g.out_neighbors(u)[i]))
+}
+
+/// Dijkstra's algorithm using the edge weights stored in `g`.
+pub fn dijkstra_weighted<V, E, W>(g: &impl WeightedGraph<V, E, W>, v: V) -> ShortestPaths<V, W>
+where
+    V: Vertex + std::hash::Hash + std::cmp::Eq,
+    E: Edge<V>,
+    W: num::Float,
+{
+    dijkstra_impl(g, v, |u, i| g.out_weights(u)[i])
+}
+
+/// `weight(u, i)` is the weight of the edge from `u` to its `i`th out-neighbor.
+fn dijkstra_impl<V, E, W, G, F>(g: &G, v: V, weight: F) -> ShortestPaths<V, W>
 where
     V: Vertex + std::hash::Hash + std::cmp::Eq,
     E: Edge<V>,
     W: num::Float,
+    G: Graph<V, E>,
+    F: Fn(V, usize) -> W,
 {
     let vu = v.index().as_();
     let n = g.nv().as_();
-    let mut visited: bv::BitVec<bv::Lsb0, u64> = bv::BitVec::repeat(false, n);
-    let mut pq = PriorityQueue::<V, OrderedFloat<W>>::new();
+    // `discovered` vertices have a tentative distance and are (or were) in
+    // the queue; `settled` ones have been popped and their distance is final.
+    let mut discovered: bv::BitVec<bv::Lsb0, u64> = bv::BitVec::repeat(false, n);
+    let mut settled: bv::BitVec<bv::Lsb0, u64> = bv::BitVec::repeat(false, n);
+    // PriorityQueue pops the largest priority, so distances are reversed to
+    // settle the nearest vertex first.
+    let mut pq = PriorityQueue::<V, Reverse<OrderedFloat<W>>>::new();
     let mut dists = vec![W::infinity(); n];
     let mut parents = vec![V::sentinel(); n];
 
     dists[vu] = W::zero();
     unsafe {
-        visited.set_unchecked(vu, true);
+        discovered.set_unchecked(vu, true);
     }
-    pq.push(v, OrderedFloat(W::zero()));
+    pq.push(v, Reverse(OrderedFloat(W::zero())));
 
-    // println!("starting pq");
-    while !pq.is_empty() {
-        let (u, _) = pq.pop().unwrap();
-        // println!("popped {}", u);
+    while let Some((u, _)) = pq.pop() {
         let uu = u.index().as_();
+        unsafe {
+            settled.set_unchecked(uu, true);
+        }
         let d = dists[uu];
-        for v in g.out_neighbors(u) {
+        for (i, v) in g.out_neighbors(u).iter().enumerate() {
             let vu = (*v).index().as_();
-            let alt = d + weights(u, *v);
-            if !visited[vu] {
+            if settled[vu] {
+                continue;
+            }
+            let alt = d + weight(u, i);
+            if !discovered[vu] {
                 unsafe {
-                    visited.set_unchecked(vu, true);
+                    discovered.set_unchecked(vu, true);
                 }
                 dists[vu] = alt;
                 parents[vu] = u;
-                pq.push(*v, OrderedFloat(alt));
+                pq.push(*v, Reverse(OrderedFloat(alt)));
             } else if alt < dists[vu] {
                 dists[vu] = alt;
                 parents[vu] = u;
-                pq.change_priority(v, OrderedFloat(alt));
-                // pq.push(*v, OrderedFloat(alt));
+                pq.change_priority(v, Reverse(OrderedFloat(alt)));
             }
         }
     }
     parents[vu] = V::sentinel();
-    dists
+    ShortestPaths { src: v, dists, parents }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{digraph, graph, random_edges};
+    use crate::traits::FromEdgeList;
+    use crate::weighted::StaticWeightedDiGraph;
+    use crate::{StaticDiGraph, StaticGraph};
+    use std::path::Path;
+
+    /// A digraph on 3000 vertices with average out-degree 10; the third
+    /// frontier holds most of the edges, so the search goes bottom-up.
+    fn dense_digraph(seed: u64) -> StaticDiGraph<u32> {
+        digraph(3000, random_edges(3000, 30000, seed))
+    }
+
+    #[test]
+    fn dijkstra_settles_nearest_first() {
+        let g = StaticWeightedDiGraph::<u32, f64>::from_weighted_edges(vec![
+            (0, 1, 1.0),
+            (0, 2, 10.0),
+            (1, 2, 1.0),
+            (2, 3, 1.0),
+        ]);
+        let sp = dijkstra_weighted(&g, 0u32);
+        assert_eq!(sp.dists, vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(sp.path_to(3), Some(vec![0, 1, 2, 3]));
+        assert_eq!(sp.predecessor(0), None);
+    }
+
+    #[test]
+    fn direction_optimizing_matches_bfs() {
+        let g = StaticDiGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        let h = StaticGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        for src in g.vertices() {
+            assert_eq!(direction_optimizing_bfs(&g, src), bfs(&g, src));
+            assert_eq!(direction_optimizing_bfs(&h, src), bfs(&h, src));
+        }
+        for seed in 0..3 {
+            let g = dense_digraph(seed);
+            for src in [0, 1, 2999].iter() {
+                assert_eq!(direction_optimizing_bfs(&g, *src), bfs(&g, *src));
+            }
+            let h = graph(3000, random_edges(3000, 15000, seed));
+            assert_eq!(direction_optimizing_bfs(&h, 7), bfs(&h, 7));
+        }
+        // A dense core with a long tail, so the search switches back to
+        // top-down once the frontier narrows.
+        let mut edges = random_edges(500, 10000, 42);
+        edges.extend((499..2000).map(|v| (v, v + 1)));
+        let h = graph(2001, edges);
+        assert_eq!(direction_optimizing_bfs(&h, 0), bfs(&h, 0));
+    }
+
+    #[test]
+    fn par_bfs_matches_bfs() {
+        let g = StaticDiGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        let h = StaticGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        for src in g.vertices() {
+            assert_eq!(par_bfs(&g, src), bfs(&g, src));
+            assert_eq!(par_bfs(&h, src), bfs(&h, src));
+        }
+        for seed in 0..3 {
+            let g = dense_digraph(seed);
+            for src in [0, 1, 2999].iter() {
+                assert_eq!(par_bfs(&g, *src), bfs(&g, *src));
+            }
+        }
+        // Vertex counts around a multiple of 64 exercise the last bitmap word.
+        for n in [63, 64, 65, 129].iter() {
+            let g = digraph(*n, (0..*n as u32 - 1).map(|v| (v, v + 1)).collect());
+            assert_eq!(par_bfs(&g, 0), bfs(&g, 0));
+            assert_eq!(par_bfs(&g, *n as u32 - 1), bfs(&g, *n as u32 - 1));
+        }
+    }
+
+    #[test]
+    fn bfs_state_is_reusable() {
+        let small = StaticDiGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        let large = dense_digraph(0);
+        let mut b = Bfs::new(small.nv() as usize).with_parents().with_order();
+        // Alternate sources and graph sizes; every run must match a fresh search.
+        for (g, src) in [(&small, 0), (&small, 3), (&large, 5), (&small, 1), (&large, 2999)].iter() {
+            b.run(*g, *src);
+            assert_eq!(b.levels(), &bfs(*g, *src)[..]);
+            assert_eq!(b.parents().unwrap().len(), g.nv() as usize);
+            assert_eq!(b.path_to(*src), Some(vec![*src]));
+        }
+        assert!(Bfs::<u32>::new(0).parents().is_none());
+        assert!(Bfs::<u32>::new(0).order().is_none());
+    }
+
+    #[test]
+    fn run_to_stops_at_the_target() {
+        // 0 -> 1 -> 2 -> 3 -> 4, plus 0 -> 5 and an unreachable 6.
+        let g = digraph(7, vec![(0, 1), (1, 2), (2, 3), (3, 4), (0, 5)]);
+        let mut b = Bfs::new(7).with_order();
+        assert!(b.run_to(&g, 0, 2));
+        assert_eq!(b.levels()[2], 2);
+        assert_eq!(b.levels()[3], u32::MAX);
+        assert_eq!(b.levels()[4], u32::MAX);
+        assert_eq!(b.order().unwrap().last(), Some(&2));
+        assert!(b.run_to(&g, 3, 3));
+        assert_eq!(b.order().unwrap(), &[3]);
+        assert!(!b.run_to(&g, 0, 6));
+        assert_eq!(b.levels(), &bfs(&g, 0)[..]);
+        assert!(!b.run_to(&g, 4, 0));
+    }
+
+    #[test]
+    fn order_and_paths_follow_levels() {
+        let g = dense_digraph(1);
+        let mut b = Bfs::new(g.nv() as usize).with_parents().with_order();
+        b.run(&g, 0);
+        let levels = b.levels().to_vec();
+        let order = b.order().unwrap();
+        // Every reached vertex appears once, in nondecreasing level order.
+        let reached = levels.iter().filter(|l| **l != u32::MAX).count();
+        assert_eq!(order.len(), reached);
+        assert!(order.windows(2).all(|w| levels[w[0] as usize] <= levels[w[1] as usize]));
+        let mut seen = order.to_vec();
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), reached);
+
+        for v in g.vertices() {
+            match b.path_to(v) {
+                Some(path) => {
+                    assert_eq!(path.len(), levels[v as usize] as usize + 1);
+                    assert_eq!((path[0], *path.last().unwrap()), (0, v));
+                    assert!(path.windows(2).all(|e| g.out_neighbors(e[0]).binary_search(&e[1]).is_ok()));
+                }
+                None => assert_eq!(levels[v as usize], u32::MAX),
+            }
+        }
+        let mut b = Bfs::new(g.nv() as usize);
+        b.run(&g, 0);
+        assert_eq!(b.path_to(0), None);
+    }
+
+    #[test]
+    fn msbfs_matches_bfs_per_source() {
+        let g = dense_digraph(2);
+        // A full batch, so the last search uses bit 63.
+        let sources: Vec<u32> = (0..64).map(|i| 47 * i).collect();
+        let mut rows = vec![vec![u32::MAX; 3000]; 64];
+        msbfs(&g, &sources, |i, v, level| {
+            assert_eq!(rows[i][v as usize], u32::MAX, "search {} visited {} twice", i, v);
+            rows[i][v as usize] = level;
+        });
+        for (i, src) in sources.iter().enumerate() {
+            assert_eq!(rows[i], bfs(&g, *src), "source {}", src);
+        }
+        msbfs(&g, &[], |_, _, _| panic!("no sources, no visits"));
+    }
+
+    #[test]
+    fn all_pairs_matches_bfs() {
+        let g = StaticDiGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        let dists = all_pairs_hop_distances(&g);
+        assert_eq!(dists.len(), g.nv() as usize);
+        for s in g.vertices() {
+            assert_eq!(dists[s as usize], bfs(&g, s));
+        }
+        // Three batches, the last one partial.
+        let g = digraph(150, random_edges(150, 400, 3));
+        let dists = all_pairs_hop_distances(&g);
+        for s in g.vertices() {
+            assert_eq!(dists[s as usize], bfs(&g, s));
+        }
+    }
+
+    #[test]
+    fn multi_source_bfs_prefers_earlier_sources() {
+        // 0 - 1 - 2: vertex 1 is one hop from both ends.
+        let g = graph(3, vec![(0, 1), (1, 2)]);
+        assert_eq!(multi_source_bfs(&g, &[2, 0]), (vec![0, 1, 0], vec![0, 2, 2]));
+        assert_eq!(multi_source_bfs(&g, &[0, 2]), (vec![0, 1, 0], vec![0, 0, 2]));
+
+        let g = graph(300, random_edges(300, 450, 5));
+        let sources = [250, 3, 17, 3, 120];
+        let per_source: Vec<Vec<u32>> = sources.iter().map(|s| bfs(&g, *s)).collect();
+        let (levels, nearest) = multi_source_bfs(&g, &sources);
+        for v in 0..300 {
+            let best = per_source.iter().map(|l| l[v]).min().unwrap();
+            assert_eq!(levels[v], best);
+            let first = sources.iter().zip(per_source.iter()).find(|(_, l)| l[v] == best).map(|(s, _)| *s);
+            match first {
+                Some(s) if best != u32::MAX => assert_eq!(nearest[v], s, "vertex {}", v),
+                _ => assert_eq!(nearest[v], u32::sentinel()),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl DfsVisitor<u32> for Recorder {
+        fn discover_vertex(&mut self, v: u32, time: usize) {
+            self.events.push(format!("discover {} at {}", v, time));
+        }
+        fn tree_edge(&mut self, u: u32, v: u32) {
+            self.events.push(format!("tree {} {}", u, v));
+        }
+        fn back_edge(&mut self, u: u32, v: u32) {
+            self.events.push(format!("back {} {}", u, v));
+        }
+        fn forward_or_cross_edge(&mut self, u: u32, v: u32) {
+            self.events.push(format!("forward/cross {} {}", u, v));
+        }
+        fn finish_vertex(&mut self, v: u32, time: usize) {
+            self.events.push(format!("finish {} at {}", v, time));
+        }
+    }
+
+    #[test]
+    fn dfs_times_and_edge_classes() {
+        // 0 -> 2 is a forward edge, 3 -> 1 a cross edge, 2 -> 0 and the
+        // self-loop on 2 are back edges, and 4 is isolated.
+        let g = digraph(5, vec![(0, 1), (1, 2), (2, 0), (2, 2), (0, 2), (3, 1)]);
+        let mut rec = Recorder::default();
+        let times = dfs(&g, &mut rec);
+        assert_eq!(times.discover, vec![0, 1, 2, 6, 8]);
+        assert_eq!(times.finish, vec![5, 4, 3, 7, 9]);
+        assert_eq!(
+            rec.events,
+            vec![
+                "discover 0 at 0", "tree 0 1", "discover 1 at 1", "tree 1 2", "discover 2 at 2",
+                "back 2 0", "back 2 2", "finish 2 at 3", "finish 1 at 4", "forward/cross 0 2",
+                "finish 0 at 5", "discover 3 at 6", "forward/cross 3 1", "finish 3 at 7",
+                "discover 4 at 8", "finish 4 at 9",
+            ]
+        );
+
+        let mut rec = Recorder::default();
+        let times = dfs_from(&g, 3, &mut rec);
+        assert_eq!(times.discover, vec![3, 1, 2, 0, usize::MAX]);
+        assert_eq!(times.finish, vec![4, 6, 5, 7, usize::MAX]);
+        assert_eq!(rec.events.iter().filter(|e| e.starts_with("tree")).count(), 3);
+        // The unit visitor ignores every event.
+        assert_eq!(dfs(&g, &mut ()).finish, vec![5, 4, 3, 7, 9]);
+    }
+
+    #[test]
+    fn dfs_on_undirected_graphs_sees_the_parent_edge() {
+        let g = graph(3, vec![(0, 1), (1, 2)]);
+        let mut rec = Recorder::default();
+        dfs(&g, &mut rec);
+        assert!(rec.events.contains(&"back 1 0".to_string()));
+        assert!(rec.events.contains(&"back 2 1".to_string()));
+        assert_eq!(rec.events.iter().filter(|e| e.starts_with("tree")).count(), 2);
+    }
+}
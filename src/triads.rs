@@ -0,0 +1,221 @@
+//! Triad census and directed triangle counts for `StaticDiGraph`.
+//!
+//! The census follows Batagelj and Mrvar, "A subquadratic triad census
+//! algorithm for large sparse networks with small maximum degree" (2001):
+//! every connected triad is visited once from its lowest-numbered edge, and
+//! the empty triads (`003`) are found by subtraction.
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::simplegraphs::SimpleVertex;
+use crate::triangles::{optimal_contiguous_partition, Parallelism};
+use crate::{StaticDiGraph, VOOR};
+
+/// The 16 isomorphism classes of directed triads, in the standard
+/// Holland–Leinhardt (MAN) order used by `TriadCensus::counts`.
+pub const TRIAD_NAMES: [&str; 16] = [
+    "003", "012", "102", "021D", "021U", "021C", "111D", "111U",
+    "030T", "030C", "201", "120D", "120U", "120C", "210", "300",
+];
+
+/// Maps the 6-bit code built by `tricode` to a 1-based index into
+/// `TRIAD_NAMES`.
+const TRICODES: [u8; 64] = [
+    1, 2, 2, 3, 2, 4, 6, 8, 2, 6, 5, 7, 3, 8, 7, 11,
+    2, 6, 4, 8, 5, 9, 9, 13, 6, 10, 9, 14, 7, 14, 12, 15,
+    2, 5, 6, 7, 6, 9, 10, 14, 4, 9, 9, 12, 8, 13, 14, 15,
+    3, 7, 8, 11, 7, 12, 14, 15, 8, 14, 13, 15, 11, 15, 15, 16,
+];
+
+/// The number of vertex triples of each triad type. `counts[i]` is the
+/// number of triads of type `TRIAD_NAMES[i]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TriadCensus {
+    pub counts: [usize; 16],
+}
+
+impl TriadCensus {
+    /// The count for the triad type called `name` (e.g. `"030C"`).
+    pub fn get(&self, name: &str) -> Option<usize> {
+        TRIAD_NAMES.iter().position(|n| *n == name).map(|i| self.counts[i])
+    }
+
+    /// `(name, count)` pairs in `TRIAD_NAMES` order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        TRIAD_NAMES.iter().cloned().zip(self.counts.iter().cloned())
+    }
+
+    /// The number of directed 3-cycles `a -> b -> c -> a`. A triad with
+    /// reciprocated edges can contain more than one (`300` contains two).
+    pub fn cyclic_triangles(&self) -> usize {
+        let c = &self.counts;
+        c[9] + c[13] + c[14] + 2 * c[15]
+    }
+
+    /// The number of transitive triangles `a -> b, b -> c, a -> c`. A triad
+    /// with reciprocated edges can contain more than one (`300` contains six).
+    pub fn transitive_triangles(&self) -> usize {
+        let c = &self.counts;
+        c[8] + 2 * c[11] + 2 * c[12] + c[13] + 3 * c[14] + 6 * c[15]
+    }
+}
+
+/// Writes the sorted union of `a` and `b` into `out`, leaving out `x`, `y`
+/// and duplicates.
+fn merge_rows<V>(a: &[V], b: &[V], x: V, y: V, out: &mut Vec<V>) where V: SimpleVertex {
+    out.clear();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let next = if j == b.len() || (i < a.len() && a[i] <= b[j]) {
+            i += 1;
+            a[i - 1]
+        } else {
+            j += 1;
+            b[j - 1]
+        };
+        if next != x && next != y && out.last() != Some(&next) {
+            out.push(next);
+        }
+    }
+}
+
+/// Encodes which of the six possible edges among `v`, `u` and `w` exist.
+#[inline]
+fn tricode<V>(g: &StaticDiGraph<V>, v: V, u: V, w: V) -> usize where V: SimpleVertex {
+    let combos = [(v, u, 1), (u, v, 2), (v, w, 4), (w, v, 8), (u, w, 16), (w, u, 32)];
+    combos.iter().filter(|(s, d, _)| g.fadj.has_index(*s, *d)).map(|(_, _, x)| x).sum()
+}
+
+/// Counts the connected triads whose lowest-numbered edge starts at a
+/// vertex in `rows`. Each connected triad of the graph is counted from
+/// exactly one row; `counts[0]` (`003`) is left at zero.
+fn census_rows<V>(g: &StaticDiGraph<V>, rows: Range<usize>) -> [usize; 16] where V: SimpleVertex {
+    let n = g.fadj.dim();
+    let mut counts = [0usize; 16];
+    let mut vnbrs: Vec<V> = vec![];
+    let mut unbrs: Vec<V> = vec![];
+    let mut both: Vec<V> = vec![];
+    for v in rows {
+        let vv = V::from(v).expect(VOOR);
+        merge_rows(g.fadj.row(vv), g.badj.row(vv), vv, vv, &mut vnbrs);
+        for u in vnbrs.iter().cloned().filter(|u| *u > vv) {
+            merge_rows(g.fadj.row(u), g.badj.row(u), u, vv, &mut unbrs);
+            merge_rows(&vnbrs, &unbrs, u, vv, &mut both);
+
+            // Triads made of the dyad {v, u} and a vertex adjacent to neither.
+            let dyad = if g.fadj.has_index(vv, u) && g.fadj.has_index(u, vv) { 2 } else { 1 };
+            counts[dyad] += n - both.len() - 2;
+
+            for w in both.iter().cloned() {
+                if u < w || (vv < w && w < u && vnbrs.binary_search(&w).is_err()) {
+                    counts[TRICODES[tricode(g, vv, u, w)] as usize - 1] += 1;
+                }
+            }
+        }
+    }
+    counts
+}
+
+/// Fills in the `003` count as whatever is left of the `n choose 3` triples.
+/// Saturates at `usize::MAX` for graphs too large to count them exactly.
+fn finish(n: usize, mut counts: [usize; 16]) -> TriadCensus {
+    let n = n as u128;
+    let total = if n < 3 { 0 } else { n * (n - 1) * (n - 2) / 6 };
+    let connected: u128 = counts.iter().map(|c| *c as u128).sum();
+    counts[0] = (total - connected).min(usize::MAX as u128) as usize;
+    TriadCensus { counts }
+}
+
+/// The triad census of `g`. Self-loops are ignored.
+pub fn triad_census<V>(g: &StaticDiGraph<V>) -> TriadCensus where V: SimpleVertex {
+    let n = g.fadj.dim();
+    finish(n, census_rows(g, 0..n))
+}
+
+/// Parallel version of `triad_census`.
+pub fn threaded_triad_census<V>(g: &StaticDiGraph<V>) -> TriadCensus where V: SimpleVertex + std::marker::Sync + std::marker::Send {
+    threaded_triad_census_par(g, &Parallelism::default())
+}
+
+/// `threaded_triad_census` with an explicit partition count and thread pool.
+pub fn threaded_triad_census_par<V>(g: &StaticDiGraph<V>, par: &Parallelism) -> TriadCensus where V: SimpleVertex + std::marker::Sync + std::marker::Send {
+    let n = g.fadj.dim();
+    par.install(|| {
+        // Work from each row grows with the square of its total degree.
+        let weights: Vec<usize> = (0..n).map(|v| {
+            let d: usize = g.fadj.row_len(v).as_() + g.badj.row_len(v).as_();
+            d * d
+        }).collect();
+        let counts = optimal_contiguous_partition(weights, par.n_partitions())
+            .into_par_iter()
+            .map(|p| census_rows(g, p))
+            .reduce(|| [0usize; 16], |mut a, b| {
+                a.iter_mut().zip(b.iter()).for_each(|(x, y)| *x += y);
+                a
+            });
+        finish(n, counts)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{digraph, random_edges};
+    use crate::traits::FromEdgeList;
+    use std::path::Path;
+
+    /// Classifies every triple directly with `tricode`.
+    fn brute_force(g: &StaticDiGraph<u32>) -> TriadCensus {
+        let n = g.fadj.dim() as u32;
+        let mut counts = [0usize; 16];
+        for a in 0..n {
+            for b in (a + 1)..n {
+                for c in (b + 1)..n {
+                    counts[TRICODES[tricode(g, a, b, c)] as usize - 1] += 1;
+                }
+            }
+        }
+        TriadCensus { counts }
+    }
+
+    fn check(g: &StaticDiGraph<u32>) {
+        let n = g.fadj.dim();
+        let expected = brute_force(g);
+        for census in [triad_census(g), threaded_triad_census(g), threaded_triad_census_par(g, &Parallelism::new().partitions(5))].iter() {
+            let total: usize = census.counts.iter().sum();
+            assert_eq!(total, if n < 3 { 0 } else { n * (n - 1) * (n - 2) / 6 });
+            assert_eq!(*census, expected);
+        }
+    }
+
+    #[test]
+    fn census_matches_brute_force() {
+        check(&StaticDiGraph::from_edge_file(Path::new("testgraph.txt")));
+        check(&digraph(0, vec![]));
+        check(&digraph(6, vec![]));
+        // Mutual, asymmetric and self-loop edges among a handful of vertices.
+        check(&digraph(8, vec![
+            (0, 1), (1, 0), (1, 2), (2, 0), (2, 3), (3, 2), (3, 4), (4, 5),
+            (5, 3), (0, 4), (6, 0), (6, 1), (6, 2), (6, 6), (7, 7),
+        ]));
+        check(&digraph(25, random_edges(25, 80, 7)));
+    }
+
+    #[test]
+    fn directed_triangles() {
+        // 0 -> 1 -> 2 -> 0 is cyclic; 3 -> 4, 4 -> 5, 3 -> 5 is transitive.
+        let g = digraph(6, vec![(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (3, 5)]);
+        let census = triad_census(&g);
+        assert_eq!(census.get("030C"), Some(1));
+        assert_eq!(census.get("030T"), Some(1));
+        assert_eq!(census.cyclic_triangles(), 1);
+        assert_eq!(census.transitive_triangles(), 1);
+        // A complete mutual triad holds both cycles and all six orderings.
+        let g = digraph(3, vec![(0, 1), (1, 0), (1, 2), (2, 1), (0, 2), (2, 0)]);
+        let census = triad_census(&g);
+        assert_eq!(census.get("300"), Some(1));
+        assert_eq!(census.cyclic_triangles(), 2);
+        assert_eq!(census.transitive_triangles(), 6);
+    }
+}
@@ -1,21 +1,23 @@
 use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use rayon::prelude::*;
 
-use crate::{SimpleVertex, StaticGraph};
+use crate::simplegraphs::SimpleVertex;
+use crate::traits::{Edge, UndirectedGraph};
 
-pub fn triangles<V>(g: &StaticGraph<V>) -> usize where V: SimpleVertex {
-    let mut dodg: Vec<Vec<V>> = Vec::with_capacity(g.nv());
+pub fn triangles<V, E, G>(g: &G) -> usize where V: SimpleVertex, E: Edge<V>, G: UndirectedGraph<V, E> {
+    let mut dodg: Vec<Vec<V>> = Vec::with_capacity(g.nv().as_());
 
-    let mut degrees = vec![V::zero(); g.nv()];
+    let mut degrees = vec![0usize; g.nv().as_()];
     let mut ntri = 0usize;
 
     // let z:Vec<V> = g.vertices().collect();
     // println!("minvert = {:?}, maxvert = {:?}", z.iter().min(), z.iter().max());
     for u in g.vertices() {
-        let degu = g.out_degree(u);
+        let degu: usize = g.out_degree(u).as_();
         degrees[u.as_()] = degu;
-        let vvec = g.out_neighbors(u).into_iter().filter(|v| {
-            let degv = g.out_degree(**v);
+        let vvec = g.out_neighbors(u).iter().filter(|v| {
+            let degv: usize = g.out_degree(**v).as_();
             degv > degu || (degv == degu && **v > u)
         }).cloned().collect();
         dodg.push(vvec);
@@ -24,12 +26,9 @@ pub fn triangles<V>(g: &StaticGraph<V>) -> usize where V: SimpleVertex {
     // println!("len(dodg) = {}", dodg.len());
     for u in g.vertices() {
         let uvec = &dodg[u.as_()];
-        let ulen = uvec.len();
-        for i in 0..ulen {
-            let v = uvec[i];
+        for (i, &v) in uvec.iter().enumerate() {
             let vvec = &dodg[v.as_()];
-            for j in (i+1)..ulen {
-                let w = uvec[j];
+            for &w in &uvec[(i+1)..] {
                 let wvec = &dodg[w.as_()];
                 let w_to_v = degrees[v.as_()] > degrees[w.as_()] || (degrees[v.as_()] == degrees[w.as_()] && v > w);
                 if (w_to_v && wvec.binary_search(&v).is_ok()) || (!w_to_v && vvec.binary_search(&w).is_ok()) {
@@ -41,12 +40,13 @@ pub fn triangles<V>(g: &StaticGraph<V>) -> usize where V: SimpleVertex {
     ntri
 }
 
-fn optimal_contiguous_partition(weights: Vec<usize>, n_partitions: usize) -> Vec<Range<usize>>
+pub(crate) fn optimal_contiguous_partition(weights: Vec<usize>, n_partitions: usize) -> Vec<Range<usize>>
 {
 
-    let mut up_bound = weights.iter().sum();
-    let mut low_bound = (up_bound-1) / n_partitions;
     let n_items = weights.len();
+    let mut up_bound: usize = weights.iter().sum();
+    // With no weight at all, every item lands in a single partition.
+    let mut low_bound = up_bound.saturating_sub(1) / n_partitions;
 
     while up_bound > low_bound + 1 {
         let search_for = (up_bound + low_bound) / 2;
@@ -90,85 +90,383 @@ fn optimal_contiguous_partition(weights: Vec<usize>, n_partitions: usize) -> Vec
 
 
 
-pub fn threaded_triangles<V>(g: &StaticGraph<V>) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send {
-    // let z:Vec<V> = g.vertices().collect();
-    // println!("minvert = {:?}, maxvert = {:?}", z.iter().min(), z.iter().max());
-    let mut bigvec: Vec<(V, Vec<V>)> = Vec::with_capacity(g.nv());
-    (0..g.nv()).into_par_iter().map(|u| {
+/// Builds the degree-ordered DAG (DODG) used by the threaded triangle
+/// counters: each edge is kept only in the row of the endpoint that is
+/// lower in (degree, id) order. Returns the degrees alongside the DODG.
+fn degree_ordered_dag<V, E, G>(g: &G) -> (Vec<usize>, graph_matrix::GraphMatrix<V>) where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    let mut bigvec: Vec<(usize, Vec<V>)> = Vec::with_capacity(g.nv().as_());
+    (0..g.nv().as_()).into_par_iter().map(|u: usize| {
         let uu = V::from(u).unwrap();
-        let degu = g.out_degree(uu);
-        let vvec: Vec<V> = g.out_neighbors(uu).into_iter().filter(|v| {
-            let degv = g.out_degree(**v);
+        let degu: usize = g.out_degree(uu).as_();
+        let vvec: Vec<V> = g.out_neighbors(uu).iter().filter(|v| {
+            let degv: usize = g.out_degree(**v).as_();
             degv > degu || (degv == degu && **v > uu)
         }).cloned().collect();
-        // if u < 5 {
-        //     println!("out_degree = {}, len(vvec) = {}", degu, vvec.len());
-        // }
         (degu, vvec)
     }).collect_into_vec(&mut bigvec);
 
-    // let s: Vec<usize> = bigvec.iter().map(|(_, v)| v.len()).collect();
-    // let ss: usize = s.into_iter().sum();
-    // println!("total sum from bigvec = {}", ss);
-
-    let (degrees, dodg) = {
-        let mut degs: Vec<V> = Vec::with_capacity(bigvec.len());
-        let mut indptr: Vec<usize> = Vec::with_capacity(bigvec.len() + 1);
-        let mut indices: Vec<V> = Vec::new();
-        // let mut acc = 0usize;
-        indptr.push(0);
-        bigvec.into_iter().for_each(|mut v| {
-            degs.push(v.0);
-            indices.append(&mut v.1);
-            indptr.push(indices.len());
-        });
-    // let isum: usize = indptr.iter().sum();
-    // println!("isum = {}", isum);
-    // println!("indices = {:?}", &indices[0..10]);
+    let mut degs: Vec<usize> = Vec::with_capacity(bigvec.len());
+    let mut indptr: Vec<usize> = Vec::with_capacity(bigvec.len() + 1);
+    let mut indices: Vec<V> = Vec::new();
+    indptr.push(0);
+    bigvec.into_iter().for_each(|mut v| {
+        degs.push(v.0);
+        indices.append(&mut v.1);
+        indptr.push(indices.len());
+    });
     let gm = graph_matrix::GraphMatrix::new(indptr, indices);
-    // println!("gm = {}", gm);
     (degs, gm)
-    };
+}
+
+/// Calls `f(u, v, w)` for every triangle whose lowest-ordered vertex `u`
+/// is in `rows`. Each triangle of the graph is found from exactly one `u`.
+fn walk_triangles<V, F>(degrees: &[usize], dodg: &graph_matrix::GraphMatrix<V>, rows: Range<usize>, mut f: F)
+where
+    V: SimpleVertex,
+    F: FnMut(V, V, V),
+{
+    for u in rows {
+        let uu = V::from(u).unwrap();
+        let uvec = dodg.row(uu);
+        for (i, &v) in uvec.iter().enumerate() {
+            let vvec = dodg.row(v);
+            for &w in &uvec[(i+1)..] {
+                if closes(degrees, dodg, vvec, v, w) {
+                    f(uu, v, w);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `v` and `w` (two out-neighbors of some `u` in the DODG) are
+/// adjacent, looking in the row of whichever is lower in (degree, id) order.
+#[inline]
+fn closes<V>(degrees: &[usize], dodg: &graph_matrix::GraphMatrix<V>, vvec: &[V], v: V, w: V) -> bool where V: SimpleVertex {
+    let w_to_v = degrees[v.as_()] > degrees[w.as_()] || (degrees[v.as_()] == degrees[w.as_()] && v > w);
+    if w_to_v {
+        dodg.row(w).binary_search(&v).is_ok()
+    } else {
+        vvec.binary_search(&w).is_ok()
+    }
+}
+
+/// Calls `f(u, v, w)` once for every triangle of `g`. `u` is the vertex of
+/// the triangle lowest in (degree, id) order; `v` and `w` are the other two,
+/// with `v < w` by vertex id (not by degree).
+pub fn for_each_triangle<V, E, G, F>(g: &G, f: F) where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync, F: FnMut(V, V, V) {
+    let (degrees, dodg) = degree_ordered_dag(g);
+    walk_triangles(&degrees, &dodg, 0..dodg.dim(), f);
+}
+
+/// Parallel version of `for_each_triangle`: `f` is called concurrently from
+/// the rayon pool, once for every triangle.
+pub fn par_for_each_triangle<V, E, G, F>(g: &G, f: F) where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync, F: Fn(V, V, V) + std::marker::Sync {
+    let (degrees, dodg) = degree_ordered_dag(g);
+    dodg_partitions(&dodg, rayon::current_num_threads()).into_par_iter().for_each(|p| {
+        walk_triangles(&degrees, &dodg, p, &f);
+    });
+}
+
+/// An iterator over the triangles of an undirected graph, yielding each one
+/// exactly once without materializing the full list, with its vertices
+/// arranged as in `for_each_triangle`. See `triangle_iter`.
+pub struct Triangles<V> {
+    degrees: Vec<usize>,
+    dodg: graph_matrix::GraphMatrix<V>,
+    u: usize,
+    i: usize,
+    j: usize,
+}
+
+impl<V> Iterator for Triangles<V> where V: SimpleVertex {
+    type Item = (V, V, V);
+
+    fn next(&mut self) -> Option<(V, V, V)> {
+        while self.u < self.dodg.dim() {
+            let uu = V::from(self.u).unwrap();
+            let uvec = self.dodg.row(uu);
+            if self.i >= uvec.len() {
+                self.u += 1;
+                self.i = 0;
+                self.j = 1;
+                continue;
+            }
+            if self.j >= uvec.len() {
+                self.i += 1;
+                self.j = self.i + 1;
+                continue;
+            }
+            let v = uvec[self.i];
+            let w = uvec[self.j];
+            self.j += 1;
+            if closes(&self.degrees, &self.dodg, self.dodg.row(v), v, w) {
+                return Some((uu, v, w));
+            }
+        }
+        None
+    }
+}
+
+/// Returns an iterator over the triangles of `g`, in the same order and
+/// orientation as `for_each_triangle`.
+pub fn triangle_iter<V, E, G>(g: &G) -> Triangles<V> where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    let (degrees, dodg) = degree_ordered_dag(g);
+    Triangles { degrees, dodg, u: 0, i: 0, j: 1 }
+}
 
-    // let (degrees, dodg): (Vec<V>, Vec<Vec<V>>) = bigvec.unzip();
-    // let foo: usize = (0..dodg.dim()).map(|r| dodg.row_len(r).as_()).sum();
-    // println!("foo = {}", foo);
-    // println!("dodg = {}", dodg);
+/// Partitions the rows of the DODG into `n_partitions` contiguous ranges of
+/// roughly equal work.
+fn dodg_partitions<V>(dodg: &graph_matrix::GraphMatrix<V>, n_partitions: usize) -> Vec<Range<usize>> where V: SimpleVertex {
     let weights: Vec<usize> = (0..dodg.dim()).map(|r| dodg.row_len(r).pow(2).as_()).collect();
-    // println!("{:?}", weights);
-    let partitions = optimal_contiguous_partition(weights, 12);
-    // println!("{:?}", partitions);
+    optimal_contiguous_partition(weights, n_partitions)
+}
+
+/// How the threaded triangle counters split and schedule their work.
+///
+/// By default work runs on the global rayon pool and is split into one
+/// partition per pool thread.
+#[derive(Clone, Copy, Default)]
+pub struct Parallelism<'a> {
+    partitions: Option<usize>,
+    pool: Option<&'a rayon::ThreadPool>,
+}
+
+impl<'a> Parallelism<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Splits the work into `n` contiguous partitions.
+    pub fn partitions(mut self, n: usize) -> Self {
+        self.partitions = Some(n);
+        self
+    }
+
+    /// Runs on `pool` instead of the global rayon pool.
+    pub fn pool(mut self, pool: &'a rayon::ThreadPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    pub fn n_partitions(&self) -> usize {
+        let n = self.partitions.unwrap_or_else(|| match self.pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        });
+        n.max(1)
+    }
+
+    pub(crate) fn install<R, F>(&self, f: F) -> R where R: Send, F: FnOnce() -> R + Send {
+        match self.pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+}
+
+pub fn threaded_triangles<V, E, G>(g: &G) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    threaded_triangles_par(g, &Parallelism::default())
+}
+
+/// `threaded_triangles` with an explicit partition count and thread pool.
+pub fn threaded_triangles_par<V, E, G>(g: &G, par: &Parallelism) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    par.install(|| {
+        let (degrees, dodg) = degree_ordered_dag(g);
+        let partitions = dodg_partitions(&dodg, par.n_partitions());
+        partitions.into_par_iter().map(|p| {
+            let mut ntri = 0usize;
+            walk_triangles(&degrees, &dodg, p, |_, _, _| ntri += 1);
+            ntri
+        }).sum()
+    })
+}
+
+/// How `threaded_triangles_with` finds the triangles closing each pair of
+/// DODG edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// For each pair `(v, w)` of `u`'s out-neighbors, binary-search one of
+    /// their rows for the other. This is what `threaded_triangles` uses.
+    BinarySearch,
+    /// For each edge `u -> v`, intersect the rows of `u` and `v` with a
+    /// sorted merge, galloping through the longer row when their lengths
+    /// are very different.
+    Merge,
+    /// Mark the row of `u` in a per-thread bitmap, then scan the row of each
+    /// out-neighbor `v` for marked vertices.
+    Marker,
+}
+
+// Gallop instead of merging when one row is this many times longer.
+const GALLOP_RATIO: usize = 32;
+
+/// |a ∩ b| for sorted slices.
+fn intersect_count<V>(a: &[V], b: &[V]) -> usize where V: SimpleVertex {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if small.is_empty() {
+        return 0;
+    }
+    if large.len() / small.len() >= GALLOP_RATIO {
+        return gallop_count(small, large);
+    }
+    let (mut i, mut j, mut n) = (0, 0, 0);
+    while i < small.len() && j < large.len() {
+        if small[i] < large[j] {
+            i += 1;
+        } else if small[i] > large[j] {
+            j += 1;
+        } else {
+            n += 1;
+            i += 1;
+            j += 1;
+        }
+    }
+    n
+}
+
+/// |small ∩ large| by exponential search in `large` for each element of `small`.
+fn gallop_count<V>(small: &[V], large: &[V]) -> usize where V: SimpleVertex {
+    let mut lo = 0;
+    let mut n = 0;
+    for x in small {
+        let mut hi = lo;
+        let mut step = 1;
+        while hi < large.len() && large[hi] < *x {
+            lo = hi + 1;
+            hi += step;
+            step *= 2;
+        }
+        let end = (hi + 1).min(large.len());
+        match large[lo..end].binary_search(x) {
+            Ok(k) => {
+                n += 1;
+                lo += k + 1;
+            }
+            Err(k) => lo += k,
+        }
+        if lo >= large.len() {
+            break;
+        }
+    }
+    n
+}
+
+/// Counts triangles with `kernel`. All kernels walk the same DODG and
+/// return the same count as `threaded_triangles`.
+pub fn threaded_triangles_with<V, E, G>(g: &G, kernel: Kernel, par: &Parallelism) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    par.install(|| kernel_triangles(g, kernel, par.n_partitions()))
+}
+
+fn kernel_triangles<V, E, G>(g: &G, kernel: Kernel, n_partitions: usize) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    let (degrees, dodg) = degree_ordered_dag(g);
+    let partitions = dodg_partitions(&dodg, n_partitions);
+    let n = dodg.dim();
     partitions.into_par_iter().map(|p| {
         let mut ntri = 0usize;
-        for u in p {
-            let uvec = dodg.row(V::from(u).unwrap());
-            let ulen = uvec.len();
-            for i in 0..ulen {
-                let v = uvec[i];
-                let vvec = dodg.row(v);
-                for j in (i+1)..ulen {
-                    // ntri += 1;
-                    let w = uvec[j];
-                    let wvec = dodg.row(w);
-                    let w_to_v = degrees[v.as_()] > degrees[w.as_()] || (degrees[v.as_()] == degrees[w.as_()] && v > w);
-                    if (w_to_v && wvec.binary_search(&v).is_ok()) || (!w_to_v && vvec.binary_search(&w).is_ok()) {
-                         ntri += 1;
+        match kernel {
+            Kernel::BinarySearch => walk_triangles(&degrees, &dodg, p, |_, _, _| ntri += 1),
+            Kernel::Merge => {
+                for u in p {
+                    let uvec = dodg.row(V::from(u).unwrap());
+                    for v in uvec {
+                        ntri += intersect_count(uvec, dodg.row(*v));
+                    }
+                }
+            }
+            Kernel::Marker => {
+                let mut marked = vec![false; n];
+                for u in p {
+                    let uvec = dodg.row(V::from(u).unwrap());
+                    uvec.iter().for_each(|v| marked[v.as_()] = true);
+                    for v in uvec {
+                        ntri += dodg.row(*v).iter().filter(|w| marked[w.as_()]).count();
                     }
+                    uvec.iter().for_each(|v| marked[v.as_()] = false);
                 }
             }
         }
-       ntri
+        ntri
     }).sum()
 }
 
-pub fn threaded_triangles_csr<V>(g: &StaticGraph<V>) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send {
+/// The number of triangles through each vertex. Each triangle is found
+/// once by the degree-ordered walk and credited to all three corners.
+pub fn vertex_triangles<V, E, G>(g: &G) -> Vec<usize> where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    let (degrees, dodg) = degree_ordered_dag(g);
+    let mut counts = vec![0usize; g.nv().as_()];
+    walk_triangles(&degrees, &dodg, 0..dodg.dim(), |u, v, w| {
+        counts[u.as_()] += 1;
+        counts[v.as_()] += 1;
+        counts[w.as_()] += 1;
+    });
+    counts
+}
+
+/// Parallel version of `vertex_triangles`.
+pub fn threaded_vertex_triangles<V, E, G>(g: &G) -> Vec<usize> where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    let (degrees, dodg) = degree_ordered_dag(g);
+    let counts: Vec<AtomicUsize> = (0..g.nv().as_()).map(|_: usize| AtomicUsize::new(0)).collect();
+    dodg_partitions(&dodg, rayon::current_num_threads()).into_par_iter().for_each(|p| {
+        walk_triangles(&degrees, &dodg, p, |u, v, w| {
+            counts[u.as_()].fetch_add(1, Ordering::Relaxed);
+            counts[v.as_()].fetch_add(1, Ordering::Relaxed);
+            counts[w.as_()].fetch_add(1, Ordering::Relaxed);
+        });
+    });
+    counts.into_iter().map(|c| c.into_inner()).collect()
+}
+
+fn wedges(d: usize) -> usize {
+    if d < 2 { 0 } else { d * (d - 1) / 2 }
+}
+
+/// The local clustering coefficient of each vertex: the fraction of pairs
+/// of its neighbors that are themselves adjacent. Vertices with degree
+/// below 2 have a coefficient of 0.
+pub fn local_clustering<V, E, G>(g: &G) -> Vec<f64> where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    threaded_vertex_triangles(g).into_iter().zip(g.vertices()).map(|(t, v)| {
+        let w = wedges(g.out_degree(v).as_());
+        if w == 0 { 0.0 } else { t as f64 / w as f64 }
+    }).collect()
+}
+
+/// The mean of `local_clustering` over all vertices.
+pub fn average_clustering<V, E, G>(g: &G) -> f64 where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    let cc = local_clustering(g);
+    if cc.is_empty() {
+        return 0.0;
+    }
+    cc.iter().sum::<f64>() / cc.len() as f64
+}
+
+/// Global transitivity: three times the number of triangles divided by the
+/// number of connected triples (wedges).
+pub fn transitivity<V, E, G>(g: &G) -> f64 where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    let w: usize = g.vertices().map(|v| wedges(g.out_degree(v).as_())).sum();
+    if w == 0 {
+        return 0.0;
+    }
+    3.0 * threaded_triangles(g) as f64 / w as f64
+}
+
+pub fn threaded_triangles_csr<V, E, G>(g: &G) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    threaded_triangles_csr_par(g, &Parallelism::default())
+}
+
+/// `threaded_triangles_csr` with an explicit partition count and thread pool.
+pub fn threaded_triangles_csr_par<V, E, G>(g: &G, par: &Parallelism) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
+    par.install(|| csr_triangles(g, par.n_partitions()))
+}
+
+fn csr_triangles<V, E, G>(g: &G, n_partitions: usize) -> usize where V: SimpleVertex + std::marker::Sync + std::marker::Send, E: Edge<V>, G: UndirectedGraph<V, E> + std::marker::Sync {
     // let z:Vec<V> = g.vertices().collect();
     // println!("minvert = {:?}, maxvert = {:?}", z.iter().min(), z.iter().max());
-    let bigvec = (0..g.nv()).into_par_iter().map(|u| {
+    let bigvec = (0..g.nv().as_()).into_par_iter().map(|u: usize| {
         let uu = V::from(u).unwrap();
-        let degu = g.out_degree(uu);
-        let vvec: Vec<V> = g.out_neighbors(uu).into_iter().filter(|v| {
-            let degv = g.out_degree(**v);
+        let degu: usize = g.out_degree(uu).as_();
+        let vvec: Vec<V> = g.out_neighbors(uu).iter().filter(|v| {
+            let degv: usize = g.out_degree(**v).as_();
             degv > degu || (degv == degu && **v > uu)
         }).cloned().collect();
         // if u < 5 {
@@ -181,27 +479,17 @@ pub fn threaded_triangles_csr<V>(g: &StaticGraph<V>) -> usize where V: SimpleVer
     // let ss: usize = s.into_iter().sum();
     // println!("total sum from bigvec = {}", ss);
 
-    let (degrees, dodg): (Vec<V>, Vec<Vec<V>>) = bigvec.unzip();
+    let (degrees, dodg): (Vec<usize>, Vec<Vec<V>>) = bigvec.unzip();
 
-    // let (degrees, dodg): (Vec<V>, Vec<Vec<V>>) = bigvec.unzip();
-    // let foo: usize = (0..dodg.dim()).map(|r| dodg.row_len(r).as_()).sum();
-    // println!("foo = {}", foo);
-    // println!("dodg = {}", dodg);
     let weights: Vec<usize> = (0..dodg.len()).map(|r| dodg[r].len()).collect();
-    // println!("{:?}", weights);
-    let partitions = optimal_contiguous_partition(weights, 12);
-    // println!("{:?}", partitions);
+    let partitions = optimal_contiguous_partition(weights, n_partitions);
     partitions.into_par_iter().map(|p| {
         let mut ntri = 0usize;
         for u in p {
             let uvec = &dodg[u];
-            let ulen = uvec.len();
-            for i in 0..ulen {
-                let v = uvec[i];
+            for (i, &v) in uvec.iter().enumerate() {
                 let vvec = &dodg[v.as_()];
-                for j in (i+1)..ulen {
-                    // ntri += 1;
-                    let w = uvec[j];
+                for &w in &uvec[(i+1)..] {
                     let wvec = &dodg[w.as_()];
                     let w_to_v = degrees[v.as_()] > degrees[w.as_()] || (degrees[v.as_()] == degrees[w.as_()] && v > w);
                     if (w_to_v && wvec.binary_search(&v).is_ok()) || (!w_to_v && vvec.binary_search(&w).is_ok()) {
@@ -213,3 +501,196 @@ pub fn threaded_triangles_csr<V>(g: &StaticGraph<V>) -> usize where V: SimpleVer
        ntri
     }).sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::graph;
+    use crate::traits::{FromEdgeList, Graph};
+    use crate::StaticGraph;
+    use std::path::Path;
+
+    /// A 40-clique `0..40`, a vertex `40` adjacent to the whole clique, and
+    /// five leaves on every clique vertex. The clique vertices outrank
+    /// vertex 40, so its DODG row (40 long) is intersected with rows of
+    /// length 1 to 39, and the shortest of those pairs gallop.
+    fn skewed() -> (StaticGraph<u32>, usize) {
+        let mut edges: Vec<(u32, u32)> = vec![];
+        let mut leaf = 41;
+        for u in 0..40 {
+            for v in (u + 1)..40 {
+                edges.push((u, v));
+            }
+            edges.push((u, 40));
+            for _ in 0..5 {
+                edges.push((u, leaf));
+                leaf += 1;
+            }
+        }
+        (graph(leaf as usize, edges), 40 * 39 * 38 / 6 + 40 * 39 / 2)
+    }
+
+    fn brute_force(g: &StaticGraph<u32>) -> usize {
+        let adj = |u: u32, v: u32| g.out_neighbors(u).binary_search(&v).is_ok();
+        let n = g.nv();
+        let mut count = 0;
+        for u in 0..n {
+            for v in (u + 1)..n {
+                for w in (v + 1)..n {
+                    if adj(u, v) && adj(v, w) && adj(u, w) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    /// Triangles through each vertex, with every pair of neighbors checked.
+    fn brute_force_vertex(g: &StaticGraph<u32>) -> Vec<usize> {
+        g.vertices().map(|u| {
+            let nbrs = g.out_neighbors(u);
+            let mut count = 0;
+            for (i, v) in nbrs.iter().enumerate() {
+                count += nbrs[i + 1..].iter().filter(|w| g.out_neighbors(*v).binary_search(w).is_ok()).count();
+            }
+            count
+        }).collect()
+    }
+
+    fn check_clustering(g: &StaticGraph<u32>) {
+        let expected = brute_force_vertex(g);
+        assert_eq!(vertex_triangles(g), expected);
+        assert_eq!(threaded_vertex_triangles(g), expected);
+
+        let cc = local_clustering(g);
+        let mut wedges = 0;
+        for v in g.vertices() {
+            let d = g.out_degree(v) as usize;
+            if d < 2 {
+                assert_eq!(cc[v as usize], 0.0);
+            } else {
+                assert!((cc[v as usize] - expected[v as usize] as f64 / (d * (d - 1) / 2) as f64).abs() < 1e-12);
+                wedges += d * (d - 1) / 2;
+            }
+        }
+        let mean = if cc.is_empty() { 0.0 } else { cc.iter().sum::<f64>() / cc.len() as f64 };
+        assert!((average_clustering(g) - mean).abs() < 1e-12);
+        let closed: usize = expected.iter().sum();
+        assert_eq!(closed, 3 * brute_force(g));
+        let t = if wedges == 0 { 0.0 } else { closed as f64 / wedges as f64 };
+        assert!((transitivity(g) - t).abs() < 1e-12);
+    }
+
+    fn check_kernels(g: &StaticGraph<u32>, expected: usize) {
+        assert_eq!(triangles(g), expected);
+        assert_eq!(threaded_triangles(g), expected);
+        for kernel in [Kernel::BinarySearch, Kernel::Merge, Kernel::Marker].iter() {
+            for par in [Parallelism::new(), Parallelism::new().partitions(1), Parallelism::new().partitions(7)].iter() {
+                assert_eq!(threaded_triangles_with(g, *kernel, par), expected, "{:?}", kernel);
+            }
+        }
+    }
+
+    #[test]
+    fn kernels_agree_on_testgraph() {
+        let g = StaticGraph::<u32>::from_edge_file(Path::new("testgraph.txt"));
+        check_kernels(&g, brute_force(&g));
+    }
+
+    #[test]
+    fn kernels_agree_on_skewed_degrees() {
+        let (g, expected) = skewed();
+        check_kernels(&g, expected);
+    }
+
+    #[test]
+    fn galloping_matches_merging() {
+        let large: Vec<u32> = (0..1000).map(|x| 3 * x).collect();
+        for small in [vec![0u32], vec![1, 2999], vec![3, 300, 301, 2997, 5000], (0..30).map(|x| 100 * x).collect()].iter() {
+            assert!(large.len() / small.len() >= GALLOP_RATIO);
+            let expected = small.iter().filter(|x| large.binary_search(x).is_ok()).count();
+            assert_eq!(intersect_count(small, &large), expected);
+            assert_eq!(gallop_count(small, &large), expected);
+        }
+    }
+
+    #[test]
+    fn clustering_matches_brute_force() {
+        check_clustering(&StaticGraph::<u32>::from_edge_file(Path::new("testgraph.txt")));
+        check_clustering(&skewed().0);
+        check_clustering(&graph(0, vec![]));
+    }
+
+    #[test]
+    fn clustering_of_low_degree_vertices() {
+        // A triangle 0-1-2 with a leaf 3 on vertex 2 and an isolated vertex 4.
+        let g = graph(5, vec![(0, 1), (1, 2), (0, 2), (2, 3)]);
+        check_clustering(&g);
+        assert_eq!(vertex_triangles(&g), vec![1, 1, 1, 0, 0]);
+        let cc = local_clustering(&g);
+        assert_eq!(cc, vec![1.0, 1.0, 1.0 / 3.0, 0.0, 0.0]);
+        assert!((average_clustering(&g) - (7.0 / 3.0) / 5.0).abs() < 1e-12);
+        assert!((transitivity(&g) - 3.0 / 5.0).abs() < 1e-12);
+        // No wedges at all.
+        let g = graph(4, vec![(0, 1), (2, 3)]);
+        assert_eq!(local_clustering(&g), vec![0.0; 4]);
+        assert_eq!(average_clustering(&g), 0.0);
+        assert_eq!(transitivity(&g), 0.0);
+    }
+
+    /// Every triangle as `(u, v, w)` with `u < v < w`, sorted.
+    fn brute_force_list(g: &StaticGraph<u32>) -> Vec<(u32, u32, u32)> {
+        let mut tris = vec![];
+        for u in g.vertices() {
+            for v in g.out_neighbors(u).iter().filter(|v| **v > u) {
+                for w in g.out_neighbors(*v).iter().filter(|w| **w > *v) {
+                    if g.out_neighbors(u).binary_search(w).is_ok() {
+                        tris.push((u, *v, *w));
+                    }
+                }
+            }
+        }
+        tris
+    }
+
+    fn sorted(mut tris: Vec<(u32, u32, u32)>) -> Vec<(u32, u32, u32)> {
+        for t in tris.iter_mut() {
+            let mut a = [t.0, t.1, t.2];
+            a.sort_unstable();
+            *t = (a[0], a[1], a[2]);
+        }
+        tris.sort_unstable();
+        tris
+    }
+
+    fn check_enumeration(g: &StaticGraph<u32>) {
+        let expected = brute_force_list(g);
+        let iterated: Vec<_> = triangle_iter(g).collect();
+        // `v < w` by id, whatever the degree order put first.
+        assert!(iterated.iter().all(|(_, v, w)| v < w));
+        let mut called = vec![];
+        for_each_triangle(g, |u, v, w| called.push((u, v, w)));
+        assert_eq!(called, iterated);
+        let collected = std::sync::Mutex::new(vec![]);
+        par_for_each_triangle(g, |u, v, w| collected.lock().unwrap().push((u, v, w)));
+        assert_eq!(sorted(iterated), expected);
+        assert_eq!(sorted(collected.into_inner().unwrap()), expected);
+    }
+
+    #[test]
+    fn enumeration_matches_brute_force() {
+        check_enumeration(&StaticGraph::<u32>::from_edge_file(Path::new("testgraph.txt")));
+        check_enumeration(&skewed().0);
+        check_enumeration(&graph(0, vec![]));
+        check_enumeration(&graph(10, vec![]));
+        // A self-loop is not a triangle.
+        check_enumeration(&graph(3, vec![(0, 1), (1, 2), (0, 2), (1, 1)]));
+    }
+
+    #[test]
+    fn empty_graphs() {
+        check_kernels(&graph(0, vec![]), 0);
+        check_kernels(&graph(10, vec![]), 0);
+    }
+}
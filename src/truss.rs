@@ -0,0 +1,311 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::simplegraphs::SimpleVertex;
+use crate::traits::Graph;
+use crate::triangles::{for_each_triangle, par_for_each_triangle};
+use crate::{csr_from_edges, EdgeIndex, StaticGraph};
+
+/// The number of triangles each edge belongs to, indexed by the edge ids
+/// of `g.edge_index()`. Each triangle is found once by the degree-ordered
+/// walk and credited to its three edges.
+pub fn edge_support<V>(g: &StaticGraph<V>) -> Vec<usize>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let idx = g.edge_index();
+    let mut support = vec![0usize; idx.len()];
+    for_each_triangle(g, |u, v, w| {
+        for (a, b) in [(u, v), (u, w), (v, w)].iter() {
+            support[idx.id(*a, *b).unwrap()] += 1;
+        }
+    });
+    support
+}
+
+/// Parallel version of `edge_support`.
+pub fn threaded_edge_support<V>(g: &StaticGraph<V>) -> Vec<usize>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let idx = g.edge_index();
+    atomic_support(g, &idx).into_iter().map(|s| s.into_inner()).collect()
+}
+
+fn atomic_support<V>(g: &StaticGraph<V>, idx: &EdgeIndex<V>) -> Vec<AtomicUsize>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let support: Vec<AtomicUsize> = (0..idx.len()).map(|_| AtomicUsize::new(0)).collect();
+    par_for_each_triangle(g, |u, v, w| {
+        for (a, b) in [(u, v), (u, w), (v, w)].iter() {
+            support[idx.id(*a, *b).unwrap()].fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    support
+}
+
+/// Calls `f(w)` for every common neighbor `w` of `u` and `v` other than
+/// `u` and `v` themselves.
+fn for_each_common_neighbor<V, F>(g: &StaticGraph<V>, u: V, v: V, mut f: F)
+where
+    V: SimpleVertex,
+    std::ops::Range<V>: Iterator<Item=V>,
+    F: FnMut(V),
+{
+    let a = g.out_neighbors(u);
+    let b = g.out_neighbors(v);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] < b[j] {
+            i += 1;
+        } else if b[j] < a[i] {
+            j += 1;
+        } else {
+            if a[i] != u && a[i] != v {
+                f(a[i]);
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+}
+
+/// Truss numbers by sequential peeling (Wang & Cheng, 2012), indexed by
+/// the edge ids of `g.edge_index()`.
+///
+/// The truss number of an edge is the largest `k` such that the edge
+/// belongs to a subgraph in which every edge is in at least `k - 2`
+/// triangles. Edges are peeled in order of support from a bucket queue,
+/// exactly as `cores::core_decomposition` peels vertices by degree.
+/// Self-loops are in no triangles and have truss number 2.
+pub fn truss_decomposition<V>(g: &StaticGraph<V>) -> Vec<usize>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let idx = g.edge_index();
+    let m = idx.len();
+    let mut sup = edge_support(g);
+    let max_sup = sup.iter().cloned().max().unwrap_or(0);
+
+    let mut bin = vec![0usize; max_sup + 1];
+    for s in sup.iter() {
+        bin[*s] += 1;
+    }
+    let mut start = 0;
+    for b in bin.iter_mut() {
+        let count = *b;
+        *b = start;
+        start += count;
+    }
+    let mut pos = vec![0usize; m];
+    let mut vert = vec![0usize; m];
+    for e in 0..m {
+        pos[e] = bin[sup[e]];
+        vert[pos[e]] = e;
+        bin[sup[e]] += 1;
+    }
+    for s in (1..=max_sup).rev() {
+        bin[s] = bin[s - 1];
+    }
+    bin[0] = 0;
+
+    let mut removed = vec![false; m];
+    for i in 0..m {
+        let e = vert[i];
+        removed[e] = true;
+        let (u, v) = idx.endpoints(e);
+        if u == v {
+            continue;
+        }
+        for_each_common_neighbor(g, u, v, |w| {
+            let e1 = idx.id(u, w).unwrap();
+            let e2 = idx.id(v, w).unwrap();
+            if removed[e1] || removed[e2] {
+                return;
+            }
+            for f in [e1, e2].iter().cloned() {
+                if sup[f] > sup[e] {
+                    let sf = sup[f];
+                    let pf = pos[f];
+                    let pw = bin[sf];
+                    let x = vert[pw];
+                    if f != x {
+                        pos[f] = pw;
+                        vert[pf] = x;
+                        pos[x] = pf;
+                        vert[pw] = f;
+                    }
+                    bin[sf] += 1;
+                    sup[f] -= 1;
+                }
+            }
+        });
+    }
+    sup.into_iter().map(|s| s + 2).collect()
+}
+
+const UNPEELED: usize = usize::MAX;
+
+/// Parallel version of `truss_decomposition` by level-synchronous peeling,
+/// following PKT (Kabir & Madduri, 2017).
+///
+/// Support is computed in parallel from the degree-ordered triangle walk.
+/// For each level `k`, every remaining edge with support `k` is peeled in
+/// rounds; peeling an edge decrements the support of the other two edges of
+/// each of its surviving triangles, and edges that drop to `k` form the
+/// next round. When two edges of a triangle are peeled in the same round,
+/// only the lower id decrements the third edge.
+pub fn par_truss_decomposition<V>(g: &StaticGraph<V>) -> Vec<usize>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let idx = g.edge_index();
+    let m = idx.len();
+    let sup = atomic_support(g, &idx);
+    // The round in which each edge was peeled.
+    let peeled: Vec<AtomicUsize> = (0..m).map(|_| AtomicUsize::new(UNPEELED)).collect();
+    let mut truss = vec![0usize; m];
+    let mut n_peeled = 0usize;
+    let mut round = 0usize;
+
+    while n_peeled < m {
+        let k = (0..m)
+            .into_par_iter()
+            .filter(|e| peeled[*e].load(Ordering::Relaxed) == UNPEELED)
+            .map(|e| sup[e].load(Ordering::Relaxed))
+            .min()
+            .unwrap();
+        let mut frontier: Vec<usize> = (0..m)
+            .into_par_iter()
+            .filter(|e| peeled[*e].load(Ordering::Relaxed) == UNPEELED && sup[*e].load(Ordering::Relaxed) <= k)
+            .collect();
+        while !frontier.is_empty() {
+            frontier.par_iter().for_each(|e| peeled[*e].store(round, Ordering::Relaxed));
+            let state = |f: usize| peeled[f].load(Ordering::Relaxed);
+            // Lowers the support of a remaining edge, but never below `k`;
+            // returns whether it just reached `k`.
+            let decrement = |f: usize| {
+                let old = sup[f].fetch_sub(1, Ordering::Relaxed);
+                if old <= k {
+                    sup[f].fetch_add(1, Ordering::Relaxed);
+                }
+                old == k + 1
+            };
+            let next: Vec<usize> = frontier
+                .par_iter()
+                .flat_map(|e| {
+                    let e = *e;
+                    let (u, v) = idx.endpoints(e);
+                    let mut dropped: Vec<usize> = vec![];
+                    if u == v {
+                        return dropped;
+                    }
+                    for_each_common_neighbor(g, u, v, |w| {
+                        let e1 = idx.id(u, w).unwrap();
+                        let e2 = idx.id(v, w).unwrap();
+                        let (s1, s2) = (state(e1), state(e2));
+                        if (s1 != UNPEELED && s1 < round) || (s2 != UNPEELED && s2 < round) {
+                            return;
+                        }
+                        if s1 == UNPEELED && s2 == UNPEELED {
+                            dropped.extend([e1, e2].iter().cloned().filter(|f| decrement(*f)));
+                        } else if s1 == UNPEELED && e < e2 {
+                            dropped.extend(Some(e1).filter(|f| decrement(*f)));
+                        } else if s2 == UNPEELED && e < e1 {
+                            dropped.extend(Some(e2).filter(|f| decrement(*f)));
+                        }
+                    });
+                    dropped
+                })
+                .collect();
+            for e in frontier.iter() {
+                truss[*e] = k + 2;
+            }
+            n_peeled += frontier.len();
+            round += 1;
+            frontier = next;
+        }
+    }
+    truss
+}
+
+/// The k-truss of `g`: the subgraph of edges with truss number at least
+/// `k`. Keeps every vertex of `g`, so vertex ids are unchanged.
+pub fn k_truss<V>(g: &StaticGraph<V>, k: usize) -> StaticGraph<V>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    truss_subgraph(g, truss_decomposition(g), k)
+}
+
+/// Parallel version of `k_truss`.
+pub fn par_k_truss<V>(g: &StaticGraph<V>, k: usize) -> StaticGraph<V>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    truss_subgraph(g, par_truss_decomposition(g), k)
+}
+
+fn truss_subgraph<V>(g: &StaticGraph<V>, truss: Vec<usize>, k: usize) -> StaticGraph<V>
+where
+    V: SimpleVertex + Send + Sync,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    let mut edges: Vec<(V, V)> = vec![];
+    for ((u, v), t) in g.edge_index().iter().zip(truss) {
+        if t >= k {
+            edges.push((u, v));
+            edges.push((v, u));
+        }
+    }
+    let (indptr, indices) = csr_from_edges(g.nv().as_(), edges);
+    StaticGraph::from_csr(indptr, indices).expect("invalid CSR")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{graph, random_edges};
+    use crate::traits::FromEdgeList;
+    use std::path::Path;
+
+    #[test]
+    fn k4_with_pendant() {
+        let g = graph(5, vec![(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3), (3, 4)]);
+        let idx = g.edge_index();
+        for truss in [truss_decomposition(&g), par_truss_decomposition(&g)].iter() {
+            for (u, v) in idx.iter() {
+                let expected = if v == 4 { 2 } else { 4 };
+                assert_eq!(truss[idx.id(u, v).unwrap()], expected, "edge ({}, {})", u, v);
+            }
+        }
+        for h in [k_truss(&g, 4), par_k_truss(&g, 4)].iter() {
+            assert_eq!(h.nv(), 5);
+            assert_eq!(h.ne(), 6);
+            assert!(h.out_neighbors(4).is_empty());
+        }
+    }
+
+    #[test]
+    fn par_truss_matches_sequential() {
+        let mut graphs = vec![
+            StaticGraph::from_edge_file(Path::new("testgraph.txt")),
+            graph(0, vec![]),
+            graph(3, vec![(0, 0), (1, 1)]),
+        ];
+        graphs.extend((0..5).map(|s| graph(30, random_edges(30, 150, s))));
+        for g in graphs.iter() {
+            assert_eq!(truss_decomposition(g), par_truss_decomposition(g));
+            assert_eq!(edge_support(g), threaded_edge_support(g));
+        }
+    }
+}
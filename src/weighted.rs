@@ -0,0 +1,434 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use crate::edgelist;
+use crate::errors::LoadError;
+use crate::mtx::{MtxEntries, Symmetry};
+use crate::simplegraphs::{SimpleEdge, SimpleVertex};
+use crate::traits::{self, DirectedGraph, UndirectedGraph, WeightedGraph};
+use crate::{transpose_csr, try_indptr, VOOR};
+
+/// `(indptr, indices, weights)`, with `weights` parallel to `indices`.
+type WeightedCsr<V, W> = (Vec<usize>, Vec<V>, Vec<W>);
+
+/// Builds a CSR matrix with `n` rows from `(src, dst, weight)` triples.
+/// Like `csr_from_edges`, each row is sorted and duplicate entries are
+/// dropped; of several weights given for the same entry, the first wins.
+fn weighted_csr<V, W>(n: usize, mut edges: Vec<(V, V, W)>) -> Result<WeightedCsr<V, W>, LoadError>
+where
+    V: SimpleVertex,
+    W: Copy,
+{
+    let mut indptr = try_indptr(n)?;
+    // A stable sort keeps duplicates in input order for `dedup_by_key`.
+    edges.sort_by_key(|e| (e.0, e.1));
+    edges.dedup_by_key(|e| (e.0, e.1));
+    for e in edges.iter() {
+        indptr[e.0.as_() + 1] += 1;
+    }
+    for i in 0..n {
+        indptr[i + 1] += indptr[i];
+    }
+    let (indices, weights) = edges.into_iter().map(|(_, d, w)| (d, w)).unzip();
+    Ok((indptr, indices, weights))
+}
+
+fn max_vertex<V, W>(edges: &[(V, V, W)]) -> usize
+where
+    V: SimpleVertex,
+{
+    edges.iter().map(|e| e.0.max(e.1).as_() + 1).max().unwrap_or(0)
+}
+
+/// An undirected graph with a weight stored for every edge.
+pub struct StaticWeightedGraph<V, W> {
+    adj: graph_matrix::GraphMatrix<V>,
+    wptr: Vec<usize>,
+    weights: Vec<W>,
+    ne: usize,
+}
+
+impl<V, W> StaticWeightedGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy,
+{
+    /// Builds a graph from `(src, dst, weight)` triples. Each edge only needs
+    /// to be given once, in either direction. An edge given more than once
+    /// (in either direction) is kept once, with the first weight given for it.
+    pub fn from_weighted_edges<I>(edges: I) -> Self
+    where
+        I: IntoIterator<Item = (V, V, W)>,
+    {
+        let edges: Vec<(V, V, W)> = edges.into_iter().collect();
+        Self::with_nv(max_vertex(&edges), edges).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds a graph with exactly `nv` vertices, failing if they cannot be
+    /// allocated.
+    pub(crate) fn with_nv(nv: usize, edges: Vec<(V, V, W)>) -> Result<Self, LoadError> {
+        // Orient every edge as `src <= dst` so that the two directions of an
+        // edge count as duplicates, then mirror all but the self-loops.
+        let mut edges: Vec<(V, V, W)> = edges.into_iter().map(|(s, d, w)| (s.min(d), s.max(d), w)).collect();
+        edges.sort_by_key(|e| (e.0, e.1));
+        edges.dedup_by_key(|e| (e.0, e.1));
+        let ne = edges.len();
+        let redges: Vec<(V, V, W)> = edges.iter().filter(|x| x.0 != x.1).map(|x| (x.1, x.0, x.2)).collect();
+        edges.extend(redges);
+        let (indptr, indices, weights) = weighted_csr(nv, edges)?;
+        let wptr = indptr.clone();
+        let adj = graph_matrix::GraphMatrix::new(indptr, indices);
+        Ok(StaticWeightedGraph { adj, wptr, weights, ne })
+    }
+
+    /// The out-neighbors of `v` paired with the weights of the connecting edges.
+    pub fn out_edges_weighted(&self, v: V) -> impl Iterator<Item = (V, W)> + '_ {
+        let ws = &self.weights[self.wptr[v.as_()]..self.wptr[v.as_() + 1]];
+        self.adj.row(v).iter().cloned().zip(ws.iter().cloned())
+    }
+}
+
+impl<V, W> StaticWeightedGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy + fmt::Display,
+{
+    /// Writes the graph as a `src dst weight` edge list. Each undirected edge
+    /// is written once, with `src <= dst`.
+    pub fn write_edge_list<O: Write>(&self, mut w: O) -> io::Result<()> {
+        writeln!(w, "# {} vertices, {} edges", self.adj.dim(), self.ne)?;
+        for u in 0..self.adj.dim() {
+            let uu = V::from(u).expect(VOOR);
+            for (v, wt) in self.out_edges_weighted(uu).filter(|(v, _)| *v >= uu) {
+                writeln!(w, "{} {} {}", uu, v, wt)?;
+            }
+        }
+        w.flush()
+    }
+}
+
+impl<V, W> traits::Graph<V, SimpleEdge<V>> for StaticWeightedGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    type VIT = std::ops::Range<V>;
+    fn nv(&self) -> V::T {
+        V::T::from(self.adj.dim()).unwrap()
+    }
+
+    fn ne(&self) -> usize {
+        self.ne
+    }
+
+    fn vertices(&self) -> std::ops::Range<V> {
+        std::ops::Range {
+            start: V::zero(),
+            end: V::from(self.adj.dim()).expect(VOOR),
+        }
+    }
+
+    fn out_degree(&self, v: V) -> V {
+        self.adj.row_len(v.to_usize().expect(VOOR))
+    }
+
+    fn in_degree(&self, v: V) -> V { self.out_degree(v) }
+
+    fn has_edge(&self, e: SimpleEdge<V>) -> bool {
+        let s = e.src;
+        let d = e.dst;
+        if self.out_degree(s) < self.out_degree(d) {
+            self.adj.has_index(s, d)
+        } else {
+            self.adj.has_index(d, s)
+        }
+    }
+
+    fn out_neighbors(&self, v: V) -> &[V] {
+        self.adj.row(v)
+    }
+
+    fn in_neighbors(&self, v: V) -> &[V] {
+        self.out_neighbors(v)
+    }
+}
+
+impl<V, W> traits::FromEdgeList for StaticWeightedGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy + FromStr,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    fn try_from_edge_reader<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        let (nv, edges) = edgelist::read_weighted_edges_nv(reader)?;
+        StaticWeightedGraph::with_nv(nv, edges)
+    }
+}
+
+impl<V, W> WeightedGraph<V, SimpleEdge<V>, W> for StaticWeightedGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    fn out_weights(&self, v: V) -> &[W] {
+        &self.weights[self.wptr[v.as_()]..self.wptr[v.as_() + 1]]
+    }
+}
+
+impl<V, W> UndirectedGraph<V, SimpleEdge<V>> for StaticWeightedGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+}
+
+/// A directed graph with a weight stored for every edge.
+pub struct StaticWeightedDiGraph<V, W> {
+    fadj: graph_matrix::GraphMatrix<V>,
+    badj: graph_matrix::GraphMatrix<V>,
+    wptr: Vec<usize>,
+    weights: Vec<W>,
+}
+
+impl<V, W> StaticWeightedDiGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy,
+{
+    /// Builds a graph from `(src, dst, weight)` triples. A duplicate edge
+    /// is kept once, with the first weight given for it.
+    pub fn from_weighted_edges<I>(edges: I) -> Self
+    where
+        I: IntoIterator<Item = (V, V, W)>,
+    {
+        let edges: Vec<(V, V, W)> = edges.into_iter().collect();
+        Self::with_nv(max_vertex(&edges), edges).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds a graph with exactly `nv` vertices, failing if they cannot be
+    /// allocated.
+    pub(crate) fn with_nv(nv: usize, edges: Vec<(V, V, W)>) -> Result<Self, LoadError> {
+        let (indptr, indices, weights) = weighted_csr(nv, edges)?;
+        let (bptr, bind) = transpose_csr(&indptr, &indices);
+        let wptr = indptr.clone();
+        let fadj = graph_matrix::GraphMatrix::new(indptr, indices);
+        let badj = graph_matrix::GraphMatrix::new(bptr, bind);
+        Ok(StaticWeightedDiGraph { fadj, badj, wptr, weights })
+    }
+
+    /// The out-neighbors of `v` paired with the weights of the connecting edges.
+    pub fn out_edges_weighted(&self, v: V) -> impl Iterator<Item = (V, W)> + '_ {
+        let ws = &self.weights[self.wptr[v.as_()]..self.wptr[v.as_() + 1]];
+        self.fadj.row(v).iter().cloned().zip(ws.iter().cloned())
+    }
+}
+
+impl<V, W> StaticWeightedDiGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy + fmt::Display,
+{
+    /// Writes the graph as a `src dst weight` edge list.
+    pub fn write_edge_list<O: Write>(&self, mut w: O) -> io::Result<()> {
+        writeln!(w, "# {} vertices, {} edges", self.fadj.dim(), self.fadj.n())?;
+        for u in 0..self.fadj.dim() {
+            let uu = V::from(u).expect(VOOR);
+            for (v, wt) in self.out_edges_weighted(uu) {
+                writeln!(w, "{} {} {}", uu, v, wt)?;
+            }
+        }
+        w.flush()
+    }
+}
+
+impl<V, W> traits::Graph<V, SimpleEdge<V>> for StaticWeightedDiGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    type VIT = std::ops::Range<V>;
+    fn nv(&self) -> V::T {
+        V::T::from(self.fadj.dim()).unwrap()
+    }
+
+    fn ne(&self) -> usize {
+        self.fadj.n()
+    }
+
+    fn vertices(&self) -> std::ops::Range<V> {
+        std::ops::Range {
+            start: V::zero(),
+            end: V::from(self.fadj.dim()).expect(VOOR),
+        }
+    }
+
+    fn out_degree(&self, v: V) -> V {
+        self.fadj.row_len(v.to_usize().expect(VOOR))
+    }
+
+    fn in_degree(&self, v: V) -> V {
+        self.badj.row_len(v.to_usize().expect(VOOR))
+    }
+
+    fn has_edge(&self, e: SimpleEdge<V>) -> bool {
+        let s = e.src;
+        let d = e.dst;
+        if self.out_degree(s) < self.in_degree(d) {
+            self.fadj.has_index(s, d)
+        } else {
+            self.badj.has_index(d, s)
+        }
+    }
+
+    fn in_neighbors(&self, v: V) -> &[V] {
+        self.badj.row(v)
+    }
+
+    fn out_neighbors(&self, v: V) -> &[V] {
+        self.fadj.row(v)
+    }
+}
+
+impl<V, W> traits::FromEdgeList for StaticWeightedDiGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy + FromStr,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    fn try_from_edge_reader<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        let (nv, edges) = edgelist::read_weighted_edges_nv(reader)?;
+        StaticWeightedDiGraph::with_nv(nv, edges)
+    }
+}
+
+impl<V, W> WeightedGraph<V, SimpleEdge<V>, W> for StaticWeightedDiGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+    fn out_weights(&self, v: V) -> &[W] {
+        &self.weights[self.wptr[v.as_()]..self.wptr[v.as_() + 1]]
+    }
+}
+
+impl<V, W> DirectedGraph<V, SimpleEdge<V>> for StaticWeightedDiGraph<V, W>
+where
+    V: SimpleVertex,
+    W: Copy,
+    std::ops::Range<V>: Iterator<Item=V>
+{
+}
+
+pub enum MtxWeightedGraph<V> {
+    Undirected(StaticWeightedGraph<V, f64>),
+    Directed(StaticWeightedDiGraph<V, f64>),
+}
+
+impl<V> MtxEntries<V>
+where
+    V: SimpleVertex,
+{
+    /// Builds a weighted graph with exactly `nv` vertices from the entries
+    /// and their values. Fails for `pattern` files, which have no values,
+    /// and if `nv` vertices cannot be allocated.
+    pub fn into_weighted_graph(self) -> Result<MtxWeightedGraph<V>, LoadError> {
+        let nv = self.nv;
+        let symmetry = self.symmetry;
+        let values = self.values.ok_or_else(|| LoadError::Format {
+            line: 0,
+            text: "pattern matrix has no values".to_string(),
+        })?;
+        let edges: Vec<(V, V, f64)> = self
+            .edges
+            .into_iter()
+            .zip(values)
+            .map(|((s, d), w)| (s, d, w))
+            .collect();
+        Ok(match symmetry {
+            Symmetry::Symmetric => MtxWeightedGraph::Undirected(StaticWeightedGraph::with_nv(nv, edges)?),
+            Symmetry::General => MtxWeightedGraph::Directed(StaticWeightedDiGraph::with_nv(nv, edges)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{FromEdgeList, Graph};
+
+    fn edges_of<G: Graph<u32, SimpleEdge<u32>> + WeightedGraph<u32, SimpleEdge<u32>, f64>>(g: &G) -> Vec<(u32, u32, f64)> {
+        g.vertices()
+            .flat_map(|u| g.out_neighbors(u).iter().zip(g.out_weights(u)).map(move |(v, w)| (u, *v, *w)))
+            .collect()
+    }
+
+    #[test]
+    fn parses_weighted_edge_lists() {
+        let text = "# a comment\n0 1 2.5\n\n1 2 -1\n";
+        let edges: Vec<(u32, u32, f64)> = edgelist::read_weighted_edges(text.as_bytes()).unwrap();
+        assert_eq!(edges, vec![(0, 1, 2.5), (1, 2, -1.0)]);
+        let g = StaticWeightedDiGraph::<u32, u8>::from_edge_reader("0 1 7\n".as_bytes());
+        assert_eq!(g.out_edges_weighted(0).collect::<Vec<_>>(), vec![(1, 7)]);
+
+        let err = StaticWeightedGraph::<u32, f64>::try_from_edge_reader("0 1 1\n1 2\n".as_bytes()).err().unwrap();
+        assert!(matches!(err, LoadError::FieldCount { line: 2, expected: 3, found: 2, .. }), "{:?}", err);
+        let err = StaticWeightedGraph::<u32, u8>::try_from_edge_reader("0 1 300\n".as_bytes()).err().unwrap();
+        assert!(matches!(err, LoadError::Parse { line: 1, ref text } if text == "300"), "{:?}", err);
+        let err = StaticWeightedDiGraph::<u8, f64>::try_from_edge_reader("0 256 1\n".as_bytes()).err().unwrap();
+        assert!(matches!(err, LoadError::VertexOutOfRange { line: 1, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn edge_list_round_trip() {
+        // Vertices 4 and 5 are isolated and only survive through the header.
+        let input = vec![(0u32, 1u32, 0.5), (1, 2, 1.5), (3, 3, 2.0), (2, 0, 4.0)];
+        let g = StaticWeightedGraph::with_nv(6, input.clone()).unwrap();
+        let mut buf: Vec<u8> = vec![];
+        g.write_edge_list(&mut buf).unwrap();
+        let h = StaticWeightedGraph::<u32, f64>::from_edge_reader(&buf[..]);
+        assert_eq!((h.nv(), h.ne()), (6, 4));
+        assert_eq!(edges_of(&h), edges_of(&g));
+
+        let g = StaticWeightedDiGraph::with_nv(6, input).unwrap();
+        let mut buf: Vec<u8> = vec![];
+        g.write_edge_list(&mut buf).unwrap();
+        let h = StaticWeightedDiGraph::<u32, f64>::from_edge_reader(&buf[..]);
+        assert_eq!((h.nv(), h.ne()), (6, 4));
+        assert_eq!(edges_of(&h), edges_of(&g));
+        for v in g.vertices() {
+            assert_eq!(h.in_neighbors(v), g.in_neighbors(v));
+        }
+
+        let err = StaticWeightedGraph::<u8, f64>::try_from_edge_reader("# 256 vertices\n0 1 1\n".as_bytes()).err().unwrap();
+        assert!(matches!(err, LoadError::VertexOutOfRange { line: 1, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn duplicates_keep_the_first_weight() {
+        // Undirected: both directions of an edge are the same edge.
+        let g = StaticWeightedGraph::from_weighted_edges(vec![(0u32, 1u32, 1.0), (1, 0, 2.0), (0, 1, 3.0), (1, 2, 4.0)]);
+        assert_eq!(g.ne(), 2);
+        assert_eq!(edges_of(&g), vec![(0, 1, 1.0), (1, 0, 1.0), (1, 2, 4.0), (2, 1, 4.0)]);
+        // Directed: only repeats of the same direction are duplicates.
+        let g = StaticWeightedDiGraph::from_weighted_edges(vec![(0u32, 1u32, 1.0), (1, 0, 2.0), (0, 1, 3.0)]);
+        assert_eq!(g.ne(), 2);
+        assert_eq!(edges_of(&g), vec![(0, 1, 1.0), (1, 0, 2.0)]);
+    }
+
+    #[test]
+    fn self_loops_are_stored_once() {
+        let g = StaticWeightedGraph::from_weighted_edges(vec![(1u32, 1u32, 5.0), (1, 1, 6.0), (0, 1, 1.0)]);
+        assert_eq!(g.ne(), 2);
+        assert_eq!(g.out_neighbors(1), &[0, 1]);
+        assert_eq!(g.out_weights(1), &[1.0, 5.0]);
+        let mut buf: Vec<u8> = vec![];
+        g.write_edge_list(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "# 2 vertices, 2 edges\n0 1 1\n1 1 5\n");
+    }
+}